@@ -0,0 +1,787 @@
+use core::slice;
+
+use crate::{
+    draw::{
+        ClockFormat, ClockMode, Style,
+        color::{self, Color},
+    },
+    io,
+};
+
+pub const MAX_TILES: usize = 16;
+pub const MAX_ZONES: usize = 4;
+/// What `--scale auto` grows to at most.
+pub const MAX_SCALE: u16 = 8;
+/// The offsets zones actually use, UTC-12 to UTC+14, in seconds east of UTC.
+pub const MIN_TZ_OFFSET: i32 = -12 * 3600;
+pub const MAX_TZ_OFFSET: i32 = 14 * 3600;
+
+#[derive(Clone)]
+pub struct ArgIter {
+    argv: *const *const u8,
+    end: *const *const u8,
+}
+
+impl ArgIter {
+    /// # Safety
+    /// `argv` must point to `argc` valid nul-terminated strings that live for the whole program.
+    pub const unsafe fn new(argc: usize, argv: *const *const u8) -> Self {
+        Self {
+            argv,
+            end: unsafe { argv.add(argc) },
+        }
+    }
+
+    /// The arguments and the environment from the stack `_start` is entered with.
+    ///
+    /// # Safety
+    /// `sp` must point at `argc`, then the `argv` pointers and a null, then the `envp` pointers and
+    /// a null, with all the strings valid for the whole program.
+    pub unsafe fn from_stack(sp: *const usize) -> (Self, Self) {
+        unsafe {
+            let argc = *sp;
+            let envp = sp.add(argc + 2);
+            let mut envc = 0;
+            while *envp.add(envc) != 0 {
+                envc += 1;
+            }
+            (Self::new(argc, sp.add(1) as _), Self::new(envc, envp as _))
+        }
+    }
+}
+
+impl Iterator for ArgIter {
+    type Item = &'static [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.argv == self.end {
+            return None;
+        }
+        unsafe {
+            let mut arg = *self.argv;
+            self.argv = self.argv.add(1);
+            let beg = arg;
+            while *arg != 0 {
+                arg = arg.add(1);
+            }
+            Some(slice::from_raw_parts(beg, arg.offset_from_unsigned(beg)))
+        }
+    }
+}
+
+pub struct Grid {
+    pub rows: u8,
+    pub cols: u8,
+    pub offsets: [i32; MAX_TILES],
+}
+
+impl Grid {
+    pub const fn len(&self) -> usize {
+        self.rows as usize * self.cols as usize
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The `--tz` clocks shown side by side, each with a label that may be empty, or stacked if any
+/// came from `--zone`.
+#[derive(Default)]
+pub struct Zones {
+    pub len: u8,
+    pub offsets: [i32; MAX_ZONES],
+    pub labels: [&'static [u8]; MAX_ZONES],
+    pub stacked: bool,
+}
+
+impl Zones {
+    /// `OFFSET` or `OFFSET=LABEL`, the offset as accepted by [`parse_tz_offset`].
+    pub fn push(&mut self, zone: &'static [u8]) -> Option<()> {
+        let (offset, label) = match zone.iter().position(|&c| c == b'=') {
+            Some(n) => (&zone[..n], &zone[n + 1..]),
+            None => (zone, &b""[..]),
+        };
+        let i = self.len as usize;
+        *self.offsets.get_mut(i)? = parse_tz_offset(offset)?;
+        self.labels[i] = label;
+        self.len += 1;
+        Some(())
+    }
+
+    pub fn has_labels(&self) -> bool {
+        self.labels[..self.len as _]
+            .iter()
+            .any(|label| !label.is_empty())
+    }
+}
+
+#[derive(Default)]
+pub struct Args {
+    pub grid: Option<Grid>,
+    pub zones: Zones,
+    pub error_fd: Option<i32>,
+    pub uring_write: bool,
+    pub style: Style,
+    pub status_bar: bool,
+    pub unicode_check: bool,
+    pub sqpoll: Option<u32>,
+    pub color_by_load: bool,
+    pub color: Option<Color>,
+    /// `--bg`: filled in behind the whole screen, or the terminal's own background if unset.
+    pub bg_color: Option<Color>,
+    /// Seconds east of UTC, from `TZ` or `-z`.
+    pub tz_offset: i32,
+    pub rainbow: bool,
+    pub format: ClockFormat,
+    pub mouse: bool,
+    pub date: bool,
+    pub hide_seconds: bool,
+    pub blink: bool,
+    /// The most the clock is scaled by, 1 unless `--scale` says otherwise. It is a maximum: a window
+    /// too small for it gets the largest scale that fits.
+    pub scale: u16,
+    /// `NO_COLOR` and `COLORTERM`.
+    pub colors: color::ColorCaps,
+    /// `fdatasync` the error fd after writing to it.
+    pub sync_log: bool,
+    /// `--stopwatch` or `--timer` instead of the time of day.
+    pub mode: ClockMode,
+    /// Seconds a `--timer` counts down from.
+    pub timer: u64,
+    /// A `--timer` that ran out counts up again.
+    pub overrun: bool,
+    /// Minutes of work and of break that `--pomodoro` takes turns with.
+    pub pomodoro: Option<(u64, u64)>,
+    /// Bells rung each time a timer runs out, the hour turns or the alarm goes off.
+    pub bell_repeat: u8,
+    /// Redraws a second while the seconds show; by default 1, or 10 for a stopwatch or timer.
+    pub fps: Option<u8>,
+    /// Print `HH:MM` once with nothing around it and exit.
+    pub plain: bool,
+    /// `--plain` prints `HH:MM:SS±HH:MM` instead.
+    pub iso8601: bool,
+    /// Print `HH:MM:SS` once and exit, as when not on a terminal.
+    pub once: bool,
+    /// End the `--plain` output with a newline.
+    pub newline: bool,
+    /// Ring and flash at the top of every hour.
+    pub chime: bool,
+    /// Seconds after local midnight to ring and flash at each day.
+    pub alarm: Option<u32>,
+    /// Keep the window title at `HH:MM`.
+    pub title: bool,
+    /// Show the time elapsed since this Unix time instead of the time of day.
+    pub epoch: Option<u64>,
+    /// What the seconds are drawn in against the hours and minutes, dim unless `--seconds-color`
+    /// names a color or `none`.
+    pub seconds_ink: Option<color::Ink>,
+    /// Draw on the alternate screen with the cursor hidden, unless `--no-alternate-screen`.
+    pub alternate_screen: bool,
+    /// Drift the clock around once no key was pressed for `idle_secs`.
+    pub screensaver: bool,
+    pub idle_secs: u32,
+    /// Centered one blank row under a single block clock.
+    pub label: Option<&'static [u8]>,
+    /// A single block clock over a dim copy of itself, one cell down and right.
+    pub shadow: bool,
+}
+
+fn invalid(msg: &str, arg: &[u8]) -> nc::Errno {
+    // in one write, so it can't interleave with anything else on stderr
+    _ = io::FdWriter::stderr().write_all_vectored(&[b"clock: ", msg.as_bytes(), b": ", arg, b"\n"]);
+    nc::EINVAL
+}
+
+pub fn parse_u64(s: &[u8]) -> Option<u64> {
+    if s.is_empty() {
+        return None;
+    }
+    let mut n: u64 = 0;
+    for &c in s {
+        if !c.is_ascii_digit() {
+            return None;
+        }
+        n = n.checked_mul(10)?.checked_add((c - b'0') as u64)?;
+    }
+    Some(n)
+}
+
+const fn in_tz_range(offset: i32) -> Option<i32> {
+    match offset {
+        MIN_TZ_OFFSET..=MAX_TZ_OFFSET => Some(offset),
+        _ => None,
+    }
+}
+
+/// `UTC` or `GMT`, alone or followed by `+N` / `-N` in whole hours east of UTC, not yet checked
+/// against [`MIN_TZ_OFFSET`] and [`MAX_TZ_OFFSET`].
+fn parse_utc_offset(s: &[u8]) -> Option<i32> {
+    let (sign, hours) = match s.strip_prefix(b"UTC").or_else(|| s.strip_prefix(b"GMT"))? {
+        [] => return Some(0),
+        [b'+', rest @ ..] => (1, rest),
+        [b'-', rest @ ..] => (-1, rest),
+        _ => return None,
+    };
+    match parse_u64(hours)? {
+        h @ 0..=14 => Some(sign * h as i32 * 3600),
+        _ => None,
+    }
+}
+
+/// A `TZ` value of the form [`parse_utc_offset`] takes, with the POSIX sign: `UTC+8` is eight
+/// hours west of UTC.
+fn parse_posix_tz(s: &[u8]) -> Option<i32> {
+    parse_utc_offset(s).and_then(|offset| in_tz_range(-offset))
+}
+
+/// `±HH:MM`, or `UTC±N` as accepted by [`parse_utc_offset`].
+pub fn parse_tz_offset(s: &[u8]) -> Option<i32> {
+    let (sign, rest) = match s {
+        [b'+', rest @ ..] => (1, rest),
+        [b'-', rest @ ..] => (-1, rest),
+        _ => return parse_utc_offset(s).and_then(in_tz_range),
+    };
+    let [h1, h2, b':', m1, m2] = *rest else {
+        return None;
+    };
+    match (parse_u64(&[h1, h2])?, parse_u64(&[m1, m2])?) {
+        (h @ 0..=14, m @ 0..=59) => in_tz_range(sign * (h * 3600 + m * 60) as i32),
+        _ => None,
+    }
+}
+
+/// `WORK[/BREAK]` in minutes, the break 5 unless given.
+pub fn parse_pomodoro(s: &[u8]) -> Option<(u64, u64)> {
+    let (work, rest) = match s.iter().position(|&c| c == b'/') {
+        Some(n) => (&s[..n], parse_u64(&s[n + 1..])?),
+        None => (s, 5),
+    };
+    match (parse_u64(work)?, rest) {
+        (work @ 1..=999, rest @ 1..=999) => Some((work, rest)),
+        _ => None,
+    }
+}
+
+/// `HH:MM:SS`, in seconds.
+pub fn parse_duration(s: &[u8]) -> Option<u64> {
+    let [h1, h2, b':', m1, m2, b':', s1, s2] = *s else {
+        return None;
+    };
+    match (
+        parse_u64(&[h1, h2])?,
+        parse_u64(&[m1, m2])?,
+        parse_u64(&[s1, s2])?,
+    ) {
+        (h, m @ 0..=59, s @ 0..=59) => Some(h * 3600 + m * 60 + s),
+        _ => None,
+    }
+}
+
+/// Switches that [`parse_bool_flag`] reads, each as `--NAME` or `--no-NAME`.
+const SWITCHES: [&[u8]; 4] = [b"mouse", b"blink", b"rainbow", b"alternate-screen"];
+
+/// Whether the last of `--NAME` and `--no-NAME` in `args` is the former, `default` if neither is
+/// there.
+pub fn parse_bool_flag(name: &[u8], args: &ArgIter, default: bool) -> bool {
+    args.clone()
+        .fold(default, |value, arg| match arg.strip_prefix(b"--") {
+            Some(arg) if arg == name => true,
+            Some(arg) if arg.strip_prefix(b"no-") == Some(name) => false,
+            _ => value,
+        })
+}
+
+/// `HH:MM` on a 24-hour clock, in seconds since midnight.
+pub fn parse_alarm(s: &[u8]) -> Option<u32> {
+    let [h1, h2, b':', m1, m2] = *s else {
+        return None;
+    };
+    match (parse_u64(&[h1, h2])?, parse_u64(&[m1, m2])?) {
+        (h @ 0..=23, m @ 0..=59) => Some((h * 3600 + m * 60) as _),
+        _ => None,
+    }
+}
+
+/// `ROWSxCOLS` followed by a colon-separated list of `ROWS * COLS` offsets.
+pub fn parse_grid(size: &[u8], zones: &[u8]) -> Option<Grid> {
+    let x = size.iter().position(|&c| c == b'x')?;
+    let rows = parse_u64(&size[..x])?;
+    let cols = parse_u64(&size[x + 1..])?;
+    if !matches!(rows.checked_mul(cols)? as usize, 1..=MAX_TILES) {
+        return None;
+    }
+    let mut grid = Grid {
+        rows: rows as _,
+        cols: cols as _,
+        offsets: [0; MAX_TILES],
+    };
+    let mut n = 0;
+    for zone in zones.split(|&c| c == b':') {
+        *grid.offsets.get_mut(n)? = parse_utc_offset(zone).and_then(in_tz_range)?;
+        n += 1;
+    }
+    (n == grid.len()).then_some(grid)
+}
+
+pub fn parse_args(mut args: ArgIter, env: ArgIter) -> io::Result<Args> {
+    let mut result = Args {
+        scale: 1,
+        bell_repeat: 1,
+        idle_secs: 30,
+        seconds_ink: Some(color::Ink::Sgr(crate::dim!())),
+        ..Default::default()
+    };
+    for var in env {
+        if let Some(tz) = var.strip_prefix(b"TZ=") {
+            // zone names and `:/etc/localtime` need zoneinfo, so those stay at UTC
+            if let Some(offset) = parse_posix_tz(tz) {
+                result.tz_offset = offset;
+            }
+        } else if let b"COLORTERM=truecolor" | b"COLORTERM=24bit" = var {
+            result.colors.truecolor = true;
+        } else if let Some([_, ..]) = var.strip_prefix(b"NO_COLOR=") {
+            result.colors.no_color = true;
+        } else if let Some(term) = var.strip_prefix(b"TERM=")
+            && (matches!(term, b"dumb" | b"linux") || term.starts_with(b"vt"))
+        {
+            result.style = Style::Ascii;
+        }
+    }
+    args.next();
+    result.mouse = parse_bool_flag(b"mouse", &args, false);
+    result.blink = parse_bool_flag(b"blink", &args, false);
+    result.rainbow = parse_bool_flag(b"rainbow", &args, false);
+    result.alternate_screen = parse_bool_flag(b"alternate-screen", &args, true);
+    while let Some(arg) = args.next() {
+        match arg {
+            b"--grid" => {
+                let (Some(size), Some(zones)) = (args.next(), args.next()) else {
+                    return Err(invalid("missing argument", arg));
+                };
+                result.grid =
+                    Some(parse_grid(size, zones).ok_or_else(|| invalid("invalid grid", zones))?);
+            }
+            b"--error-fd" => {
+                let Some(fd) = args.next() else {
+                    return Err(invalid("missing argument", arg));
+                };
+                match parse_u64(fd) {
+                    Some(fd @ 0..=0x7fff_ffff) => result.error_fd = Some(fd as _),
+                    _ => return Err(invalid("invalid file descriptor", fd)),
+                }
+            }
+            b"--style" => {
+                result.style = match args.next() {
+                    Some(b"block") => Style::Block,
+                    Some(b"braun") => Style::Braun,
+                    Some(b"ascii") => Style::Ascii,
+                    Some(b"binary") => Style::Binary,
+                    Some(b"compact") => Style::Compact,
+                    Some(style) => return Err(invalid("unknown style", style)),
+                    None => return Err(invalid("missing argument", arg)),
+                }
+            }
+            b"--scale" => {
+                result.scale = match args.next() {
+                    Some(b"auto") => MAX_SCALE,
+                    Some(scale) => match parse_u64(scale) {
+                        Some(n) if (1..=MAX_SCALE as u64).contains(&n) => n as _,
+                        _ => return Err(invalid("invalid scale", scale)),
+                    },
+                    None => return Err(invalid("missing argument", arg)),
+                }
+            }
+            b"--timer" => {
+                let Some(duration) = args.next() else {
+                    return Err(invalid("missing argument", arg));
+                };
+                result.timer = parse_duration(duration)
+                    .ok_or_else(|| invalid("invalid duration", duration))?;
+                result.mode = ClockMode::Timer;
+            }
+            // the lengths are optional, so the next argument only counts if it isn't a flag
+            b"--pomodoro" => {
+                let phases = match args.clone().next().filter(|arg| !arg.starts_with(b"-")) {
+                    Some(phases) => {
+                        args.next();
+                        parse_pomodoro(phases).ok_or_else(|| invalid("invalid pomodoro", phases))?
+                    }
+                    None => (25, 5),
+                };
+                result.pomodoro = Some(phases);
+                result.timer = phases.0 * 60;
+                result.mode = ClockMode::Timer;
+            }
+            b"--bell-repeat" | b"--bell-count" => {
+                let Some(n) = args.next() else {
+                    return Err(invalid("missing argument", arg));
+                };
+                match parse_u64(n) {
+                    Some(n @ 0..=0xff) => result.bell_repeat = n as _,
+                    _ => return Err(invalid("invalid count", n)),
+                }
+            }
+            b"--fps" => {
+                let Some(n) = args.next() else {
+                    return Err(invalid("missing argument", arg));
+                };
+                match parse_u64(n) {
+                    Some(n @ 1..=60) => result.fps = Some(n as _),
+                    _ => return Err(invalid("invalid fps", n)),
+                }
+            }
+            b"--idle-secs" => {
+                let Some(n) = args.next() else {
+                    return Err(invalid("missing argument", arg));
+                };
+                match parse_u64(n) {
+                    Some(n @ 1..=0xffff_ffff) => result.idle_secs = n as _,
+                    _ => return Err(invalid("invalid idle time", n)),
+                }
+            }
+            b"--sqpoll" => {
+                let Some(idle_ms) = args.next() else {
+                    return Err(invalid("missing argument", arg));
+                };
+                match parse_u64(idle_ms) {
+                    Some(idle_ms @ 0..=0xffff_ffff) => result.sqpoll = Some(idle_ms as _),
+                    _ => return Err(invalid("invalid idle time", idle_ms)),
+                }
+            }
+            b"--color" => {
+                let Some(name) = args.next() else {
+                    return Err(invalid("missing argument", arg));
+                };
+                result.color =
+                    Some(color::parse(name).ok_or_else(|| invalid("invalid color", name))?);
+            }
+            b"--bg" | b"--background" => {
+                let Some(name) = args.next() else {
+                    return Err(invalid("missing argument", arg));
+                };
+                result.bg_color =
+                    Some(color::parse(name).ok_or_else(|| invalid("invalid color", name))?);
+            }
+            b"--seconds-color" => {
+                let Some(name) = args.next() else {
+                    return Err(invalid("missing argument", arg));
+                };
+                result.seconds_ink = match name {
+                    b"none" => None,
+                    b"dim" => Some(color::Ink::Sgr(crate::dim!())),
+                    _ => Some(color::Ink::Color(
+                        color::parse(name).ok_or_else(|| invalid("invalid color", name))?,
+                    )),
+                };
+            }
+            b"--tz" | b"--zone" => {
+                let Some(zone) = args.next() else {
+                    return Err(invalid("missing argument", arg));
+                };
+                let zones = &mut result.zones;
+                zones
+                    .push(zone)
+                    .ok_or_else(|| invalid("invalid zone", zone))?;
+                // a stacked clock is always labeled, by the zone itself if nothing else
+                if arg == b"--zone" {
+                    zones.stacked = true;
+                    let label = &mut zones.labels[zones.len as usize - 1];
+                    if label.is_empty() {
+                        *label = zone;
+                    }
+                }
+            }
+            b"--alarm" => {
+                let Some(time) = args.next() else {
+                    return Err(invalid("missing argument", arg));
+                };
+                result.alarm =
+                    Some(parse_alarm(time).ok_or_else(|| invalid("invalid alarm", time))?);
+            }
+            b"--label" => {
+                let Some(label) = args.next() else {
+                    return Err(invalid("missing argument", arg));
+                };
+                result.label = Some(label);
+            }
+            b"--epoch" => {
+                let Some(ts) = args.next() else {
+                    return Err(invalid("missing argument", arg));
+                };
+                result.epoch = Some(parse_u64(ts).ok_or_else(|| invalid("invalid epoch", ts))?);
+            }
+            b"-z" => {
+                let Some(offset) = args.next() else {
+                    return Err(invalid("missing argument", arg));
+                };
+                result.tz_offset =
+                    parse_tz_offset(offset).ok_or_else(|| invalid("invalid offset", offset))?;
+            }
+            b"--ascii" => result.style = Style::Ascii,
+            b"--binary" => result.style = Style::Binary,
+            b"--status-bar" => result.status_bar = true,
+            b"--title" => result.title = true,
+            b"--chime" | b"--bell" => result.chime = true,
+            b"--plain" => result.plain = true,
+            b"--newline" => result.newline = true,
+            b"--once" => result.once = true,
+            b"--iso8601" => (result.plain, result.iso8601) = (true, true),
+            b"-1" | b"--12h" => result.format = ClockFormat::TwelveHour,
+            b"-s" | b"--hide-seconds" => result.hide_seconds = true,
+            b"--seconds" => result.hide_seconds = false,
+            b"-d" | b"--date" => result.date = true,
+            b"--color-by-load" => result.color_by_load = true,
+            b"--stopwatch" => result.mode = ClockMode::Stopwatch,
+            b"--overrun" => result.overrun = true,
+            b"--sync-log" => result.sync_log = true,
+            b"--uring-write" => result.uring_write = true,
+            b"--unicode-check" => result.unicode_check = true,
+            b"--screensaver" => result.screensaver = true,
+            b"--shadow" => result.shadow = true,
+            _ if SWITCHES.iter().any(|&name| {
+                let arg = arg.strip_prefix(b"--").unwrap_or_default();
+                arg.strip_prefix(b"no-").unwrap_or(arg) == name
+            }) => {}
+            _ => return Err(invalid("unknown argument", arg)),
+        }
+    }
+    if result.epoch.is_some()
+        && (result.mode != ClockMode::Wall || result.grid.is_some() || result.zones.len > 0)
+    {
+        return Err(invalid("only goes with a single clock", b"--epoch"));
+    }
+    let single_only: Option<&[u8]> = match (result.label, result.shadow) {
+        (Some(_), _) => Some(b"--label"),
+        (None, true) => Some(b"--shadow"),
+        (None, false) => None,
+    };
+    if let Some(flag) = single_only
+        && (result.mode != ClockMode::Wall
+            || result.grid.is_some()
+            || result.zones.len > 0
+            || result.epoch.is_some())
+    {
+        return Err(invalid("only goes with a single clock", flag));
+    }
+    match (result.mode, &result.grid, result.zones.len) {
+        (ClockMode::Stopwatch, Some(_), _) => {
+            Err(invalid("can't be used with --grid", b"--stopwatch"))
+        }
+        (ClockMode::Timer, Some(_), _) => Err(invalid("can't be used with --grid", b"--timer")),
+        (ClockMode::Wall, Some(_), 1..) => Err(invalid("can't be used with --grid", b"--tz")),
+        (ClockMode::Stopwatch | ClockMode::Timer, _, 1..) => {
+            Err(invalid("only goes with the time of day", b"--tz"))
+        }
+        _ => Ok(result),
+    }
+}
+
+#[test]
+fn test_parse_grid() {
+    let grid = parse_grid(b"2x3", b"UTC+0:UTC+1:UTC+2:UTC-3:UTC+4:UTC+5").unwrap();
+    assert_eq!((grid.rows, grid.cols), (2, 3));
+    assert_eq!(grid.offsets[..6], [0, 3600, 7200, -10800, 14400, 18000]);
+    assert!(parse_grid(b"2x3", b"UTC+0:UTC+1").is_none());
+    assert!(parse_grid(b"1x2", b"UTC+0:UTC+1:UTC+2").is_none());
+    assert!(parse_grid(b"1x1", b"EST+0").is_none());
+    assert!(parse_grid(b"0x1", b"").is_none());
+    assert!(parse_grid(b"5x5", b"UTC+0").is_none());
+    assert!(parse_grid(b"99999999999x99999999999", b"UTC").is_none());
+}
+
+#[test]
+fn test_parse_tz_offset() {
+    assert_eq!(parse_tz_offset(b"+05:30"), Some(19800));
+    assert_eq!(parse_tz_offset(b"-07:00"), Some(-25200));
+    assert_eq!(parse_tz_offset(b"UTC+0"), Some(0));
+    assert_eq!(parse_tz_offset(b"UTC-3"), Some(-10800));
+    assert_eq!(parse_tz_offset(b"UTC"), Some(0));
+    assert_eq!(parse_tz_offset(b"GMT+1"), Some(3600));
+    assert_eq!(parse_tz_offset(b"+14:00"), Some(MAX_TZ_OFFSET));
+    assert_eq!(parse_tz_offset(b"-12:00"), Some(MIN_TZ_OFFSET));
+    for s in [
+        &b"+14:59"[..],
+        b"-12:30",
+        b"-14:00",
+        b"UTC-13",
+        b"05:30",
+        b"+5:30",
+        b"+05:60",
+        b"+15:00",
+        b"-07",
+        b"UTC+",
+        b"Europe/Berlin",
+        b"",
+    ] {
+        assert_eq!(parse_tz_offset(s), None);
+    }
+}
+
+#[test]
+fn test_parse_bool_flag() {
+    let argv = [
+        c"clock",
+        c"--blink",
+        c"--mouse",
+        c"--no-blink",
+        c"--grid",
+        c"--rainbow",
+    ];
+    let argv = argv.map(|arg| arg.as_ptr() as *const u8);
+    let args = unsafe { ArgIter::new(argv.len(), argv.as_ptr()) };
+    assert!(!parse_bool_flag(b"blink", &args, true));
+    assert!(parse_bool_flag(b"mouse", &args, false));
+    assert!(parse_bool_flag(b"date", &args, true));
+    assert!(!parse_bool_flag(b"rain", &args, false));
+}
+
+#[test]
+fn test_parse_pomodoro() {
+    assert_eq!(parse_pomodoro(b"25"), Some((25, 5)));
+    assert_eq!(parse_pomodoro(b"50/10"), Some((50, 10)));
+    for s in [&b"0"[..], b"25/0", b"25/", b"/5", b"1000", b"25/5/5", b""] {
+        assert_eq!(parse_pomodoro(s), None);
+    }
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration(b"00:25:00"), Some(1500));
+    assert_eq!(parse_duration(b"01:02:03"), Some(3723));
+    assert_eq!(parse_duration(b"99:59:59"), Some(359_999));
+    for s in [
+        &b"00:60:00"[..],
+        b"00:00:60",
+        b"1:00:00",
+        b"00:25",
+        b"",
+        b"00:2a:00",
+    ] {
+        assert_eq!(parse_duration(s), None);
+    }
+}
+
+#[test]
+fn test_zones() {
+    let mut zones = Zones::default();
+    assert_eq!(zones.push(b"+00:00=UTC"), Some(()));
+    assert_eq!(zones.push(b"UTC-5"), Some(()));
+    assert_eq!(zones.push(b"-05:00=EST=x"), Some(()));
+    assert_eq!(zones.push(b"EST"), None);
+    assert_eq!(zones.len, 3);
+    assert_eq!(zones.offsets[..3], [0, -18000, -18000]);
+    assert_eq!(zones.labels[..3], [&b"UTC"[..], b"", b"EST=x"]);
+    assert!(zones.has_labels());
+    assert_eq!(zones.push(b"America/New_York"), None);
+    zones.push(b"GMT").unwrap();
+    assert_eq!(zones.offsets[3], 0);
+    assert_eq!(zones.push(b"+03:00"), None);
+}
+
+#[test]
+fn test_parse_alarm() {
+    assert_eq!(parse_alarm(b"00:00"), Some(0));
+    assert_eq!(parse_alarm(b"07:05"), Some(7 * 3600 + 5 * 60));
+    assert_eq!(parse_alarm(b"23:59"), Some(86340));
+    for s in [
+        &b"24:00"[..],
+        b"7:65",
+        b"07:65",
+        b"7:05",
+        b"07:5",
+        b"0705",
+        b"",
+    ] {
+        assert_eq!(parse_alarm(s), None);
+    }
+}
+
+#[test]
+fn test_from_stack() {
+    let p = |s: &'static core::ffi::CStr| s.as_ptr() as usize;
+    let stack = [
+        3,
+        p(c"clock"),
+        p(c"--mouse"),
+        p(c"--no-alternate-screen"),
+        0,
+        p(c"TERM=dumb"),
+        0,
+    ];
+    let (args, env) = unsafe { ArgIter::from_stack(stack.as_ptr()) };
+    assert_eq!(args.clone().nth(2), Some(&b"--no-alternate-screen"[..]));
+    assert_eq!(env.clone().count(), 1);
+    let parsed = parse_args(args, env).unwrap();
+    assert!(parsed.mouse && !parsed.alternate_screen && matches!(parsed.style, Style::Ascii));
+}
+
+#[test]
+fn test_parse_posix_tz() {
+    assert_eq!(parse_posix_tz(b"UTC+8"), Some(-8 * 3600));
+    assert_eq!(parse_posix_tz(b"GMT-2"), Some(2 * 3600));
+    assert_eq!(parse_posix_tz(b"UTC"), Some(0));
+    assert_eq!(parse_posix_tz(b"UTC-14"), Some(MAX_TZ_OFFSET));
+    assert_eq!(parse_posix_tz(b"UTC+14"), None);
+    for tz in [c"TZ=Europe/Berlin", c"TZ=:/etc/localtime", c"TZ="] {
+        let env = [tz.as_ptr() as *const u8];
+        let argv = [c"clock".as_ptr() as *const u8];
+        let parsed = unsafe {
+            parse_args(
+                ArgIter::new(1, argv.as_ptr()),
+                ArgIter::new(1, env.as_ptr()),
+            )
+        };
+        assert_eq!(parsed.map(|args| args.tz_offset), Ok(0));
+    }
+}
+
+#[test]
+fn test_parse_background() {
+    let parse = |argv: &[&core::ffi::CStr]| {
+        let mut ptrs = [core::ptr::null(); 3];
+        for (ptr, arg) in ptrs.iter_mut().zip(argv) {
+            *ptr = arg.as_ptr() as *const u8;
+        }
+        unsafe {
+            parse_args(
+                ArgIter::new(argv.len(), ptrs.as_ptr()),
+                ArgIter::new(0, ptrs.as_ptr()),
+            )
+        }
+        .map(|args| args.bg_color)
+    };
+    assert!(matches!(parse(&[c"clock"]), Ok(None)));
+    assert!(matches!(
+        parse(&[c"clock", c"--bg", c"black"]),
+        Ok(Some(Color::Normal(color::Literal::Black)))
+    ));
+    assert!(matches!(
+        parse(&[c"clock", c"--background", c"br_red"]),
+        Ok(Some(Color::Bright(color::Literal::Red)))
+    ));
+    assert!(parse(&[c"clock", c"--bg", c"nope"]).is_err());
+}
+
+#[test]
+fn test_parse_seconds() {
+    let parse = |argv: &[&core::ffi::CStr]| {
+        let mut ptrs = [core::ptr::null(); 3];
+        for (ptr, arg) in ptrs.iter_mut().zip(argv) {
+            *ptr = arg.as_ptr() as *const u8;
+        }
+        unsafe {
+            parse_args(
+                ArgIter::new(argv.len(), ptrs.as_ptr()),
+                ArgIter::new(0, ptrs.as_ptr()),
+            )
+        }
+        .map(|args| args.hide_seconds)
+    };
+    assert_eq!(parse(&[c"clock", c"-s"]), Ok(true));
+    assert_eq!(parse(&[c"clock", c"-s", c"--seconds"]), Ok(false));
+    assert_eq!(
+        parse(&[c"clock", c"--seconds", c"--hide-seconds"]),
+        Ok(true)
+    );
+}
@@ -1,11 +1,26 @@
 use crate::io::{self, Write};
 
+pub mod calendar;
 pub mod color;
 
 pub const COLOR_SEQUENCE_SISE: usize = 19;
 
+/// The index [`Context::draw_colored`] asks `colors` about for the label after the glyphs.
+pub const LABEL: usize = usize::MAX;
+
 pub struct Context<Writer: Write> {
     pub writer: Writer,
+    frame: Option<Frame>,
+    scale: usize,
+}
+
+const FRAME_GLYPHS: usize = 11;
+
+/// What the last positioned draw left on screen: each glyph by address and column.
+struct Frame {
+    position: (u64, u64),
+    glyphs: [(usize, u64); FRAME_GLYPHS],
+    width: u64,
 }
 
 #[derive(Clone, Copy)]
@@ -42,65 +57,870 @@ fn block(n: usize) -> &'static [u8] {
     unsafe { &BLOCKS.get_unchecked(..n * 3) }
 }
 
+fn hashes(n: usize) -> &'static [u8] {
+    const HASHES: [u8; 5] = [b'#'; 5];
+    unsafe { HASHES.get_unchecked(..n) }
+}
+
 impl<Writer: Write> Context<Writer> {
     pub const fn new(writer: Writer) -> Self {
-        Self { writer }
+        Self {
+            writer,
+            frame: None,
+            scale: 1,
+        }
     }
 
-    fn space(&mut self, n: usize) -> io::Result<()> {
-        self.writer.write_all(space(n))
+    /// Forgets the last frame, for when the screen was cleared or recolored under it.
+    pub fn invalidate(&mut self) {
+        self.frame = None;
     }
 
-    fn block(&mut self, n: usize) -> io::Result<()> {
-        self.writer.write_all(block(n))
+    pub const fn scale(&self) -> usize {
+        self.scale
     }
 
-    fn do_draw(&mut self, Draw(data): Draw) -> io::Result<()> {
-        match data.signum() {
-            1 => self.block(data as _),
-            -1 => self.space(-data as _),
-            _ => Ok(()),
+    /// Draws every glyph cell as a `scale` by `scale` square from now on.
+    pub fn set_scale(&mut self, scale: usize) {
+        if scale != self.scale {
+            self.scale = scale;
+            self.invalidate();
         }
     }
 
-    pub fn draw<R: IntoIterator<Item = &'static DrawLineN>>(
+    pub fn draw<G: Glyph + 'static, R: IntoIterator<Item = &'static G>>(
         &mut self,
+        position: Option<(u64, u64)>,
         margin_left: Option<&[u8]>,
         string: impl Fn() -> R,
     ) -> io::Result<()> {
-        for line in 0..LINE_COUNT {
+        self.draw_colored(position, margin_left, string, |_| None, None)
+    }
+
+    /// Like [`Self::draw`], switching to `colors(i)` before each row of the `i`th glyph and
+    /// writing `label` after the last row in `colors(LABEL)`, or whatever color is set by then.
+    /// The label is scaled like a glyph row, so it takes the last `scale` rows. With a
+    /// `position`, only what changed since the last frame there is drawn.
+    pub fn draw_colored<G: Glyph + 'static, R: IntoIterator<Item = &'static G>>(
+        &mut self,
+        position: Option<(u64, u64)>,
+        margin_left: Option<&[u8]>,
+        string: impl Fn() -> R,
+        colors: impl Fn(usize) -> Option<color::Ink>,
+        label: Option<&[u8]>,
+    ) -> io::Result<()> {
+        if let Some(position) = position {
+            return self.draw_changed(position, string, colors, label);
+        }
+        for line in 0..G::ROWS * self.scale {
             if let Some(x) = margin_left {
                 self.writer.write_all(x)?;
             }
-            let string = string();
-            for &draw_line_n in string {
-                let draw_list = draw_line_n[line];
-                for draw in draw_list {
-                    self.do_draw(draw)?;
+            for (i, glyph) in string().into_iter().enumerate() {
+                if let Some(ink) = colors(i) {
+                    color::write_ink(&mut self.writer, ink)?;
                 }
-                self.do_draw(Draw::off(1))?;
+                glyph.draw_row(&mut self.writer, line / self.scale, self.scale)?;
+            }
+            if let Some(label) = label
+                && line >= (G::ROWS - 1) * self.scale
+            {
+                self.write_label(label, &colors)?;
             }
             self.writer.write_all(b"\n")?;
         }
         Ok(())
     }
+
+    fn draw_changed<G: Glyph + 'static, R: IntoIterator<Item = &'static G>>(
+        &mut self,
+        (row, col): (u64, u64),
+        string: impl Fn() -> R,
+        colors: impl Fn(usize) -> Option<color::Ink>,
+        label: Option<&[u8]>,
+    ) -> io::Result<()> {
+        let last = self
+            .frame
+            .take()
+            .filter(|frame| frame.position == (row, col));
+        let mut frame = Frame {
+            position: (row, col),
+            glyphs: [(0, 0); FRAME_GLYPHS],
+            width: 0,
+        };
+        let (scale, rows) = (self.scale, G::ROWS * self.scale);
+        for (i, glyph) in string().into_iter().enumerate() {
+            let cell = (glyph as *const G as usize, col + frame.width);
+            frame.glyphs[i] = cell;
+            frame.width += (glyph.width() * scale) as u64;
+            if last.as_ref().is_some_and(|last| last.glyphs[i] == cell) {
+                continue;
+            }
+            if let Some(ink) = colors(i) {
+                color::write_ink(&mut self.writer, ink)?;
+            }
+            for line in 0..rows {
+                crate::cursor_position_to(&mut self.writer, row + line as u64, cell.1)?;
+                glyph.draw_row(&mut self.writer, line / scale, scale)?;
+            }
+        }
+        // narrower digits leave the end of the old frame behind
+        let stale = last.map_or(0, |last| last.width.saturating_sub(frame.width));
+        for line in 0..rows {
+            let label = label.filter(|_| line >= rows - scale);
+            if stale == 0 && label.is_none() {
+                continue;
+            }
+            crate::cursor_position_to(&mut self.writer, row + line as u64, col + frame.width)?;
+            if let Some(label) = label {
+                self.write_label(label, &colors)?;
+            }
+            for _ in 0..stale {
+                self.writer.write_all(b" ")?;
+            }
+        }
+        self.frame = Some(frame);
+        Ok(())
+    }
+
+    /// One row of `label`, each of its cells `scale` wide.
+    fn write_label(
+        &mut self,
+        label: &[u8],
+        colors: &impl Fn(usize) -> Option<color::Ink>,
+    ) -> io::Result<()> {
+        if let Some(ink) = colors(LABEL) {
+            color::write_ink(&mut self.writer, ink)?;
+        }
+        for cell in label.chunks(1) {
+            repeat(&mut self.writer, cell, self.scale)?;
+        }
+        Ok(())
+    }
+}
+
+pub trait Glyph {
+    const ROWS: usize;
+    /// Writes `row` with every cell repeated `scale` times.
+    fn draw_row(&self, writer: &mut impl Write, row: usize, scale: usize) -> io::Result<()>;
+    /// Columns taken by each row at scale 1, the trailing space included.
+    fn width(&self) -> usize;
+}
+
+fn repeat(writer: &mut impl Write, bytes: &[u8], times: usize) -> io::Result<()> {
+    for _ in 0..times {
+        writer.write_all(bytes)?;
+    }
+    Ok(())
+}
+
+const fn block_width(glyph: &DrawLineN) -> usize {
+    let [a, b, c] = glyph[0];
+    (a.0.unsigned_abs() + b.0.unsigned_abs() + c.0.unsigned_abs()) as usize + 1
+}
+
+impl Glyph for DrawLineN {
+    const ROWS: usize = LINE_COUNT;
+    fn width(&self) -> usize {
+        block_width(self)
+    }
+    fn draw_row(&self, writer: &mut impl Write, row: usize, scale: usize) -> io::Result<()> {
+        draw_block_row(self, writer, row, scale, block)
+    }
+}
+
+fn draw_block_row(
+    glyph: &DrawLineN,
+    writer: &mut impl Write,
+    row: usize,
+    scale: usize,
+    fill: fn(usize) -> &'static [u8],
+) -> io::Result<()> {
+    for Draw(data) in glyph[row] {
+        match data.signum() {
+            1 => repeat(writer, fill(data as _), scale)?,
+            -1 => repeat(writer, space(-data as _), scale)?,
+            _ => {}
+        }
+    }
+    repeat(writer, space(1), scale)
+}
+
+/// A block glyph drawn with `#` for terminals that can't show `█`.
+#[repr(transparent)]
+pub struct Ascii(DrawLineN);
+
+impl Ascii {
+    pub const fn new(glyph: &'static DrawLineN) -> &'static Self {
+        unsafe { &*(glyph as *const DrawLineN as *const Self) }
+    }
+}
+
+impl Glyph for Ascii {
+    const ROWS: usize = LINE_COUNT;
+    fn width(&self) -> usize {
+        block_width(&self.0)
+    }
+    fn draw_row(&self, writer: &mut impl Write, row: usize, scale: usize) -> io::Result<()> {
+        draw_block_row(&self.0, writer, row, scale, hashes)
+    }
+}
+
+/// A block glyph that moves over its blank cells instead of writing spaces to them.
+#[repr(transparent)]
+pub struct Overlay(DrawLineN);
+
+impl Overlay {
+    const fn new(glyph: &'static DrawLineN) -> &'static Self {
+        unsafe { &*(glyph as *const DrawLineN as *const Self) }
+    }
+}
+
+impl Glyph for Overlay {
+    const ROWS: usize = LINE_COUNT;
+    fn width(&self) -> usize {
+        block_width(&self.0)
+    }
+    fn draw_row(&self, writer: &mut impl Write, row: usize, scale: usize) -> io::Result<()> {
+        for Draw(data) in self.0[row] {
+            match data.signum() {
+                1 => repeat(writer, block(data as _), scale)?,
+                -1 => crate::cursor_move(
+                    writer,
+                    (-data as usize * scale) as _,
+                    crate::Direction::Right,
+                )?,
+                _ => {}
+            }
+        }
+        crate::cursor_move(writer, scale as _, crate::Direction::Right)
+    }
+}
+
+pub type ThinGlyph = [&'static [u8]; 3];
+
+impl Glyph for ThinGlyph {
+    const ROWS: usize = 3;
+    fn width(&self) -> usize {
+        self[0].len() + 1
+    }
+    fn draw_row(&self, writer: &mut impl Write, row: usize, scale: usize) -> io::Result<()> {
+        for &c in self[row] {
+            repeat(writer, &[c], scale)?;
+        }
+        repeat(writer, space(1), scale)
+    }
+}
+
+/// One BCD digit as a column of four LEDs, the 8 bit on top.
+pub struct Bits(u8);
+
+impl Glyph for Bits {
+    const ROWS: usize = 4;
+    fn width(&self) -> usize {
+        3
+    }
+    fn draw_row(&self, writer: &mut impl Write, row: usize, scale: usize) -> io::Result<()> {
+        let led = match self.0 & 8 >> row {
+            0 => "░░",
+            _ => "██",
+        };
+        repeat(writer, led.as_bytes(), scale)?;
+        repeat(writer, space(1), scale)
+    }
+}
+
+/// A block glyph squeezed into three rows: a bitmap of five pixel rows, each cell showing two of
+/// them with half blocks. The leftmost column is the highest bit.
+pub struct Compact {
+    width: u8,
+    rows: [u8; LINE_COUNT],
+}
+
+impl Glyph for Compact {
+    const ROWS: usize = 3;
+    fn width(&self) -> usize {
+        self.width as usize + 1
+    }
+    fn draw_row(&self, writer: &mut impl Write, row: usize, scale: usize) -> io::Result<()> {
+        let top = self.rows[row * 2];
+        let bottom = self.rows.get(row * 2 + 1).copied().unwrap_or(0);
+        for bit in (0..self.width).rev().map(|n| 1 << n) {
+            let cell = match (top & bit != 0, bottom & bit != 0) {
+                (true, true) => "█",
+                (true, false) => "▀",
+                (false, true) => "▄",
+                (false, false) => " ",
+            };
+            repeat(writer, cell.as_bytes(), scale)?;
+        }
+        repeat(writer, space(1), scale)
+    }
+}
+
+const fn compact(rows: [u8; LINE_COUNT]) -> Compact {
+    Compact { width: 3, rows }
+}
+
+const COMPACT_DIGITS: [Compact; 10] = [
+    compact([0b111, 0b101, 0b101, 0b101, 0b111]),
+    compact([0b010, 0b010, 0b010, 0b010, 0b010]),
+    compact([0b111, 0b001, 0b111, 0b100, 0b111]),
+    compact([0b111, 0b001, 0b111, 0b001, 0b111]),
+    compact([0b101, 0b101, 0b111, 0b001, 0b001]),
+    compact([0b111, 0b100, 0b111, 0b001, 0b111]),
+    compact([0b111, 0b100, 0b111, 0b101, 0b111]),
+    compact([0b111, 0b001, 0b001, 0b001, 0b001]),
+    compact([0b111, 0b101, 0b111, 0b101, 0b111]),
+    compact([0b111, 0b101, 0b111, 0b001, 0b111]),
+];
+
+const COMPACT_COLON: Compact = Compact {
+    width: 1,
+    rows: [0, 1, 0, 1, 0],
+};
+const COMPACT_BLANK_COLON: Compact = Compact {
+    width: 1,
+    rows: [0; LINE_COUNT],
+};
+const COMPACT_DOT: Compact = Compact {
+    width: 1,
+    rows: [0, 0, 0, 0, 1],
+};
+
+const BITS: [Bits; 10] = [
+    Bits(0),
+    Bits(1),
+    Bits(2),
+    Bits(3),
+    Bits(4),
+    Bits(5),
+    Bits(6),
+    Bits(7),
+    Bits(8),
+    Bits(9),
+];
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum Style {
+    #[default]
+    Block,
+    Braun,
+    /// The block layout drawn with `#`.
+    Ascii,
+    /// A BCD binary clock, one column of LEDs per digit.
+    Binary,
+    /// The block digits in three rows of half blocks, for short terminals.
+    Compact,
+}
+
+impl Style {
+    /// Columns and rows taken by the rendered clock face.
+    pub const fn size(self, seconds: bool) -> (u16, u16) {
+        match self {
+            Style::Block | Style::Ascii => (content_width(seconds), CLOCK_ROWS as _),
+            Style::Braun => (13, 3),
+            Style::Binary if seconds => (17, 4),
+            Style::Binary => (11, 4),
+            Style::Compact if seconds => (27, 3),
+            Style::Compact => (17, 3),
+        }
+    }
+
+    /// How many glyphs of [`draw_time`] and the like are drawn, the seconds and the colon before
+    /// them only with `show_seconds`.
+    pub const fn visible_glyphs(self, show_seconds: bool) -> usize {
+        match (self, show_seconds) {
+            (Style::Block | Style::Ascii | Style::Compact, true) => 8,
+            (Style::Block | Style::Ascii | Style::Compact, false) => 5,
+            (Style::Binary, true) => 6,
+            (Style::Binary, false) => 4,
+            (Style::Braun, _) => 5,
+        }
+    }
+
+    /// How many glyphs of [`draw_time`] and the like come before the seconds.
+    pub const fn first_seconds_glyph(self) -> usize {
+        match self {
+            Style::Block | Style::Ascii | Style::Compact => 6,
+            Style::Braun => 5,
+            Style::Binary => 4,
+        }
+    }
+
+    /// The next one for the `m` key.
+    pub const fn next(self) -> Self {
+        match self {
+            Style::Block => Style::Braun,
+            Style::Braun => Style::Ascii,
+            Style::Ascii => Style::Binary,
+            Style::Binary => Style::Compact,
+            Style::Compact => Style::Block,
+        }
+    }
+
+    /// Like [`Self::size`], for the `HH:MM:SS.cc` of a stopwatch. Binary stopwatches use the block
+    /// font.
+    pub const fn stopwatch_size(self) -> (u16, u16) {
+        match self {
+            Style::Block | Style::Ascii | Style::Binary => (STOPWATCH_COLS as _, CLOCK_ROWS as _),
+            Style::Braun => (29, 3),
+            Style::Compact => (37, 3),
+        }
+    }
+}
+
+/// Columns of `HH:MM:SS` in the block font, without the space after the last digit.
+pub const CLOCK_COLS: usize = 39;
+pub const CLOCK_ROWS: usize = LINE_COUNT;
+/// Columns of `HH:MM:SS.cc`, [`CLOCK_COLS`] and then a dot and two digits.
+pub const STOPWATCH_COLS: usize = CLOCK_COLS + block_width(&DOT) + 2 * block_width(&DIGITS[0]);
+
+const _: () = assert!(matches!(width_of(b"00:00:00"), Some(w) if w as usize == CLOCK_COLS));
+
+/// Columns the block clock is centered by, `HH:MM:SS` or `HH:MM`.
+pub const fn content_width(seconds: bool) -> u16 {
+    // `:SS` is a colon and two digits
+    let seconds_cols = block_width(&COLON) + 2 * block_width(&DIGITS[0]);
+    if seconds {
+        CLOCK_COLS as _
+    } else {
+        (CLOCK_COLS - seconds_cols) as _
+    }
+}
+
+/// Glyphs in [`TimeDisplay`]: `HH:MM:SS`.
+pub const TIME_DISPLAY_SIZE: usize = 8;
+
+/// What [`draw_time`] returns, the glyphs of `HH:MM:SS`; drop the last three for `HH:MM`.
+#[derive(Clone, Copy)]
+pub struct TimeDisplay([&'static DrawLineN; TIME_DISPLAY_SIZE]);
+
+impl TimeDisplay {
+    pub fn map<U>(self, f: impl FnMut(&'static DrawLineN) -> U) -> [U; TIME_DISPLAY_SIZE] {
+        self.0.map(f)
+    }
+}
+
+impl core::ops::Deref for TimeDisplay {
+    type Target = [&'static DrawLineN];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<[&'static DrawLineN]> for TimeDisplay {
+    fn as_ref(&self) -> &[&'static DrawLineN] {
+        &self.0
+    }
+}
+
+impl IntoIterator for TimeDisplay {
+    type Item = &'static DrawLineN;
+    type IntoIter = core::array::IntoIter<&'static DrawLineN, TIME_DISPLAY_SIZE>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// The block glyph for one of `0-9`, `:`, `.`, `-` and space.
+pub const fn glyph(c: u8) -> Option<&'static DrawLineN> {
+    let digits: &'static [DrawLineN; 10] = &DIGITS;
+    Some(match c {
+        b'0'..=b'9' => &digits[(c - b'0') as usize],
+        b':' => &COLON,
+        b'.' => &DOT,
+        b'-' => &MINUS,
+        b' ' => &BLANK_COLON,
+        _ => return None,
+    })
+}
+
+/// Columns `bytes` take in the block font, without the space after the last glyph, or `None` if
+/// [`glyph`] doesn't know one of them.
+pub const fn width_of(bytes: &[u8]) -> Option<u16> {
+    let mut width = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match glyph(bytes[i]) {
+            Some(glyph) => width += block_width(glyph),
+            None => return None,
+        }
+        i += 1;
+    }
+    Some(width.saturating_sub(1) as _)
+}
+
+/// What [`glyphs_for`] returns: as many block glyphs as a frame keeps track of, at most.
+#[derive(Clone, Copy)]
+pub struct Text {
+    glyphs: [&'static DrawLineN; FRAME_GLYPHS],
+    len: usize,
+}
+
+impl core::ops::Deref for Text {
+    type Target = [&'static DrawLineN];
+
+    fn deref(&self) -> &Self::Target {
+        &self.glyphs[..self.len]
+    }
 }
 
-pub fn draw_time(seconds: isize) -> [&'static DrawLineN; 8] {
-    let [s, min, h] = time(seconds);
-    let arr = unsafe {
-        [
-            DIGITS.get_unchecked((h / 10) as usize),
-            DIGITS.get_unchecked((h % 10) as usize),
-            &COLON,
-            DIGITS.get_unchecked((min / 10) as usize),
-            DIGITS.get_unchecked((min % 10) as usize),
-            &COLON,
-            DIGITS.get_unchecked((s / 10) as usize),
-            DIGITS.get_unchecked((s % 10) as usize),
-        ]
+/// `bytes` in the block font, for [`Context::draw`]; `None` if [`glyph`] doesn't know one of them
+/// or there are more than [`FRAME_GLYPHS`].
+pub fn glyphs_for(bytes: &[u8]) -> Option<Text> {
+    let mut text = Text {
+        glyphs: [&BLANK_COLON; FRAME_GLYPHS],
+        len: bytes.len(),
     };
-    arr
+    for (slot, &c) in text.glyphs.get_mut(..bytes.len())?.iter_mut().zip(bytes) {
+        *slot = glyph(c)?;
+    }
+    Some(text)
+}
+
+pub fn draw_time(seconds: isize, config: Config) -> TimeDisplay {
+    let [s, min, h] = time(config.format.apply(seconds));
+    let colon = if config.colon_hidden(seconds) {
+        b' '
+    } else {
+        b':'
+    };
+    let digit = |n: isize| b'0' + n as u8;
+    let text = [
+        digit(h / 10),
+        digit(h % 10),
+        colon,
+        digit(min / 10),
+        digit(min % 10),
+        colon,
+        digit(s / 10),
+        digit(s % 10),
+    ];
+    let glyphs = glyphs_for(&text).unwrap();
+    TimeDisplay(core::array::from_fn(|i| glyphs[i]))
+}
+
+/// The block clock at `(row, col)` over a dim copy of itself one cell down and right, both in
+/// `fg`, with `label` after the last row as in [`Context::draw_colored`].
+pub fn draw_time_with_shadow(
+    ctx: &mut Context<impl Write>,
+    (row, col): (u64, u64),
+    seconds: isize,
+    config: Config,
+    fg: Option<color::Ink>,
+    label: Option<&[u8]>,
+) -> io::Result<()> {
+    let content = draw_time(seconds, config);
+    let len = Style::Block.visible_glyphs(config.show_seconds);
+    if let Some(fg) = fg {
+        color::write_ink(&mut ctx.writer, fg)?;
+    }
+    ctx.writer.write_all(crate::dim!())?;
+    ctx.draw(Some((row + 1, col + 1)), None, || {
+        content[..len].iter().copied()
+    })?;
+    ctx.writer.write_all(crate::normal_intensity!())?;
+    ctx.draw_colored(
+        Some((row, col)),
+        None,
+        || content[..len].iter().map(|&glyph| Overlay::new(glyph)),
+        |_| None,
+        label,
+    )
+}
+
+/// `HH:MM` at `offset` seconds east of UTC, for `--plain`.
+pub fn draw_time_minimal(secs: isize, offset: i32) -> [u8; 5] {
+    let [_, min, h] = time((secs + offset as isize).rem_euclid(24 * 3600));
+    let mut buf = *b"00:00";
+    zero_padded(&mut buf[..2], h as _);
+    zero_padded(&mut buf[3..], min as _);
+    buf
+}
+
+/// `HH:MM:SS±HH:MM`, the time at `offset_secs` east of UTC followed by that offset.
+pub fn draw_time_iso8601(unix_secs: i64, offset_secs: i32) -> [u8; 14] {
+    let local = (unix_secs + offset_secs as i64).rem_euclid(24 * 3600);
+    let [s, min, h] = time(local as _);
+    let offset = offset_secs.unsigned_abs() / 60;
+    let mut buf = *b"00:00:00+00:00";
+    zero_padded(&mut buf[..2], h as _);
+    zero_padded(&mut buf[3..5], min as _);
+    zero_padded(&mut buf[6..8], s as _);
+    if offset_secs < 0 {
+        buf[8] = b'-';
+    }
+    zero_padded(&mut buf[9..11], offset / 60);
+    zero_padded(&mut buf[12..], offset % 60);
+    buf
+}
+
+/// `(days, hours, minutes, seconds)` in `secs`.
+pub fn decompose_elapsed(secs: u64) -> (u32, u8, u8, u8) {
+    let days = (secs / (24 * 3600)).min(u32::MAX as _) as u32;
+    let [s, min, h] = time((secs % (24 * 3600)) as _);
+    (days, h as _, min as _, s as _)
+}
+
+/// `DDD:HH:MM:SS` for `secs` elapsed, the days stopping at 999.
+pub fn draw_elapsed(secs: u64) -> [u8; 12] {
+    let (days, h, min, s) = decompose_elapsed(secs);
+    let mut buf = *b"000:00:00:00";
+    zero_padded(&mut buf[..3], days.min(999));
+    zero_padded(&mut buf[4..6], h as _);
+    zero_padded(&mut buf[7..9], min as _);
+    zero_padded(&mut buf[10..], s as _);
+    buf
+}
+
+/// `YYYY-MM-DD` for the day `seconds` falls on.
+pub fn draw_date(seconds: isize) -> [u8; 10] {
+    let calendar::Date {
+        year, month, day, ..
+    } = calendar::from_unix(seconds as _);
+    let mut buf = *b"0000-00-00";
+    zero_padded(&mut buf[..4], year.rem_euclid(10000) as _);
+    zero_padded(&mut buf[5..7], month as _);
+    zero_padded(&mut buf[8..], day as _);
+    buf
+}
+
+fn zero_padded(digits: &mut [u8], mut n: u32) {
+    for c in digits.iter_mut().rev() {
+        *c = b'0' + (n % 10) as u8;
+        n /= 10;
+    }
+}
+
+/// `label` centered one blank row under the block clock drawn at `(row, col)`, and under its
+/// shadow with `shadow`.
+pub fn draw_label_under(
+    ctx: &mut Context<impl Write>,
+    (row, col): (u64, u64),
+    config: Config,
+    shadow: bool,
+    label: &[u8],
+) -> io::Result<()> {
+    let (width, height) = Style::Block.size(config.show_seconds);
+    let scale = ctx.scale();
+    let row = row + (height as usize * scale) as u64 + shadow as u64 + 1;
+    crate::cursor_position_to(&mut ctx.writer, row, col)?;
+    write_centered_line(&mut ctx.writer, label, width as usize * scale)
+}
+
+/// `line` in the middle of `width` columns, then a newline.
+pub fn write_centered_line(writer: &mut impl Write, line: &[u8], width: usize) -> io::Result<()> {
+    let padding = width.saturating_sub(line.len()) / 2;
+    crate::cursor_move(writer, padding as _, crate::Direction::Right)?;
+    writer.write_all(line)?;
+    writer.write_all(b"\n")
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum ClockMode {
+    #[default]
+    Wall,
+    Timer,
+    Stopwatch,
+}
+
+impl ClockMode {
+    pub const fn name(self) -> &'static [u8] {
+        match self {
+            ClockMode::Wall => b"WALL",
+            ClockMode::Timer => b"TIMER",
+            ClockMode::Stopwatch => b"STOPWATCH",
+        }
+    }
+}
+
+/// `[MODE] TZ:+HH:MM Nfps`, cut to the terminal width, at the current cursor position.
+pub fn draw_status_bar(
+    writer: &mut impl Write,
+    mode: ClockMode,
+    fps: u8,
+    tz_offset: i32,
+    ws_col: u16,
+) -> io::Result<()> {
+    let mut limited = io::LimitWriter::new(&mut *writer, ws_col as _);
+    let offset = tz_offset.unsigned_abs() / 60;
+    let (h, min) = (offset / 60, offset % 60);
+    limited.write_all(b"[")?;
+    limited.write_all(mode.name())?;
+    limited.write_all(if tz_offset < 0 { b"] TZ:-" } else { b"] TZ:+" })?;
+    limited.write_u64_padded(h as _, 2, b'0')?;
+    limited.write_all(b":")?;
+    limited.write_u64_padded(min as _, 2, b'0')?;
+    limited.write_all(b" ")?;
+    limited.write_u64(fps as _)?;
+    limited.write_all(b"fps")?;
+    writer.write_all(crate::cursor_erase_to_eol!())
+}
+
+/// `HH:MM` in a 2x3 ASCII font, after the Braun BC02 wall clock.
+pub fn draw_time_braun_style(seconds: isize, config: Config) -> [&'static ThinGlyph; 5] {
+    let [_, min, h] = time(config.format.apply(seconds));
+    [
+        &THIN_DIGITS[(h / 10) as usize],
+        &THIN_DIGITS[(h % 10) as usize],
+        match config.colon_hidden(seconds) {
+            true => &THIN_BLANK_COLON,
+            false => &THIN_COLON,
+        },
+        &THIN_DIGITS[(min / 10) as usize],
+        &THIN_DIGITS[(min % 10) as usize],
+    ]
+}
+
+/// The digits of `HH:MM:SS` as BCD columns, hours tens first.
+pub fn draw_binary(seconds: isize, config: Config) -> [&'static Bits; 6] {
+    let [s, min, h] = time(config.format.apply(seconds));
+    [h / 10, h % 10, min / 10, min % 10, s / 10, s % 10].map(|n| &BITS[n as usize])
+}
+
+/// `HH:MM:SS` in the font of [`Style::Compact`]; drop the last three for `HH:MM`.
+pub fn draw_time_compact(seconds: isize, config: Config) -> [&'static Compact; 8] {
+    let [s, min, h] = time(config.format.apply(seconds));
+    let colon = match config.colon_hidden(seconds) {
+        true => &COMPACT_BLANK_COLON,
+        false => &COMPACT_COLON,
+    };
+    let digit = |n: isize| &COMPACT_DIGITS[n as usize];
+    [
+        digit(h / 10),
+        digit(h % 10),
+        colon,
+        digit(min / 10),
+        digit(min % 10),
+        colon,
+        digit(s / 10),
+        digit(s % 10),
+    ]
+}
+
+/// `HH:MM:SS.cc` for a stopwatch reading, the hours going round at 100.
+pub fn draw_stopwatch(centiseconds: u64) -> [&'static DrawLineN; 11] {
+    stopwatch_glyphs(centiseconds, &DIGITS, &COLON, &DOT)
+}
+
+/// [`draw_stopwatch`] in the font of [`draw_time_compact`].
+pub fn draw_stopwatch_compact(centiseconds: u64) -> [&'static Compact; 11] {
+    stopwatch_glyphs(centiseconds, &COMPACT_DIGITS, &COMPACT_COLON, &COMPACT_DOT)
+}
+
+/// [`draw_stopwatch`] in the font of [`draw_time_braun_style`].
+pub fn draw_stopwatch_braun_style(centiseconds: u64) -> [&'static ThinGlyph; 11] {
+    stopwatch_glyphs(centiseconds, &THIN_DIGITS, &THIN_COLON, &THIN_DOT)
+}
+
+fn stopwatch_glyphs<G>(
+    centiseconds: u64,
+    digits: &'static [G; 10],
+    colon: &'static G,
+    dot: &'static G,
+) -> [&'static G; 11] {
+    let (cs, seconds) = (centiseconds % 100, centiseconds / 100);
+    let [s, min, _] = time(seconds as _);
+    let h = seconds / 3600 % 100;
+    let digit = |n: u64| &digits[n as usize % 10];
+    [
+        digit(h / 10),
+        digit(h),
+        colon,
+        digit(min as u64 / 10),
+        digit(min as _),
+        colon,
+        digit(s as u64 / 10),
+        digit(s as _),
+        dot,
+        digit(cs / 10),
+        digit(cs),
+    ]
+}
+
+/// How [`draw_time`] and [`draw_time_braun_style`] lay out the time, and the options
+/// around it that come from the command line.
+#[derive(Clone, Copy)]
+pub struct Config {
+    pub format: ClockFormat,
+    /// Blank the colons on odd seconds.
+    pub blink_colon: bool,
+    pub show_seconds: bool,
+    /// The date line under the clock.
+    pub show_date: bool,
+    pub fg_color: color::Color,
+    /// `None` keeps the terminal's own background.
+    pub bg_color: Option<color::Color>,
+    pub colors: color::ColorCaps,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            format: ClockFormat::TwentyFourHour,
+            blink_colon: false,
+            show_seconds: true,
+            show_date: false,
+            fg_color: color::Color::Bright(color::Literal::Blue),
+            bg_color: None,
+            colors: color::ColorCaps::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Columns of the block face this draws; the AM/PM label and the date go outside it.
+    pub const fn content_width(&self) -> u16 {
+        content_width(self.show_seconds)
+    }
+
+    const fn colon_hidden(self, seconds: isize) -> bool {
+        self.blink_colon && seconds.rem_euclid(2) == 1
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum ClockFormat {
+    #[default]
+    TwentyFourHour,
+    TwelveHour,
+}
+
+impl ClockFormat {
+    pub const fn toggle(self) -> Self {
+        match self {
+            ClockFormat::TwentyFourHour => ClockFormat::TwelveHour,
+            ClockFormat::TwelveHour => ClockFormat::TwentyFourHour,
+        }
+    }
+
+    pub fn apply(self, seconds: isize) -> isize {
+        match self {
+            ClockFormat::TwentyFourHour => seconds,
+            ClockFormat::TwelveHour => twelve_hour(seconds),
+        }
+    }
+
+    /// `AM` or `PM` for the 12-hour clock.
+    pub fn label(self, seconds: isize) -> Option<&'static [u8]> {
+        match self {
+            ClockFormat::TwentyFourHour => None,
+            ClockFormat::TwelveHour if time(seconds)[2] < 12 => Some(b"AM"),
+            ClockFormat::TwelveHour => Some(b"PM"),
+        }
+    }
+}
+
+/// Shifts `seconds` so the hour reads 1 to 12 instead of 0 to 23.
+pub fn twelve_hour(seconds: isize) -> isize {
+    match seconds.rem_euclid(24 * 3600) / 3600 {
+        0 => seconds + 12 * 3600,
+        13.. => seconds - 12 * 3600,
+        _ => seconds,
+    }
 }
 
 #[must_use]
@@ -163,6 +983,23 @@ const DIGITS: [DrawLineN; 10] = [
     ],
 ];
 
+const THIN_DIGITS: [ThinGlyph; 10] = [
+    [b"/\\", b"||", b"\\/"],
+    [b" |", b" |", b" |"],
+    [b"-\\", b" /", b"/_"],
+    [b"-\\", b"-<", b"_/"],
+    [b"/|", b"-+", b" |"],
+    [b"|-", b"`\\", b"_/"],
+    [b"/ ", b"|\\", b"\\/"],
+    [b"-/", b" /", b"/ "],
+    [b"/\\", b")(", b"\\/"],
+    [b"/\\", b"\\|", b" /"],
+];
+
+const THIN_COLON: ThinGlyph = [b" ", b":", b" "];
+const THIN_BLANK_COLON: ThinGlyph = [b" "; 3];
+const THIN_DOT: ThinGlyph = [b" ", b" ", b"."];
+
 const COLON: DrawLineN = [
     [Draw::off(1), Draw::NOP, Draw::NOP],
     [Draw::on(1), Draw::NOP, Draw::NOP],
@@ -170,3 +1007,412 @@ const COLON: DrawLineN = [
     [Draw::on(1), Draw::NOP, Draw::NOP],
     [Draw::off(1), Draw::NOP, Draw::NOP],
 ];
+
+const BLANK_COLON: DrawLineN = [[Draw::off(1), Draw::NOP, Draw::NOP]; LINE_COUNT];
+
+const MINUS: DrawLineN = [
+    [Draw::off(3), Draw::NOP, Draw::NOP],
+    [Draw::off(3), Draw::NOP, Draw::NOP],
+    [Draw::on(3), Draw::NOP, Draw::NOP],
+    [Draw::off(3), Draw::NOP, Draw::NOP],
+    [Draw::off(3), Draw::NOP, Draw::NOP],
+];
+
+const DOT: DrawLineN = [
+    [Draw::off(1), Draw::NOP, Draw::NOP],
+    [Draw::off(1), Draw::NOP, Draw::NOP],
+    [Draw::off(1), Draw::NOP, Draw::NOP],
+    [Draw::off(1), Draw::NOP, Draw::NOP],
+    [Draw::on(1), Draw::NOP, Draw::NOP],
+];
+
+#[test]
+fn test_draw_time_braun_style() {
+    let mut buf = [0; 128];
+    let mut ctx = Context::new(io::ArrayWriter::new(&mut buf));
+    let content = draw_time_braun_style(12 * 3600 + 34 * 60 + 56, Config::default());
+    ctx.draw(None, None, || content).unwrap();
+    let len = ctx.writer.len;
+    assert_eq!(
+        buf[..len],
+        *b" | -\\   -\\ /| \n |  / : -< -+ \n | /_   _/  | \n"
+    );
+}
+
+#[test]
+fn test_draw_stopwatch() {
+    let mut buf = [0; 128];
+    let mut ctx = Context::new(io::ArrayWriter::new(&mut buf));
+    let content = draw_stopwatch_braun_style(((12 * 60 + 34) * 60 + 56) * 100 + 7);
+    ctx.draw(None, None, || content).unwrap();
+    let len = ctx.writer.len;
+    assert_eq!(
+        buf[..len],
+        *b" | -\\   -\\ /|   |- /    /\\ -/ \n |  / : -< -+ : `\\ |\\   ||  / \n | /_   _/  |   _/ \\/ . \\/ /  \n"
+    );
+    assert_eq!(Style::Block.stopwatch_size().0, 53);
+}
+
+#[test]
+fn test_draw_compact() {
+    let mut buf = [0; 512];
+    let mut ctx = Context::new(io::ArrayWriter::new(&mut buf));
+    ctx.draw(None, None, || &COMPACT_DIGITS).unwrap();
+    let len = ctx.writer.len;
+    let expected = "█▀█  █  ▀▀█ ▀▀█ █ █ █▀▀ █▀▀ ▀▀█ █▀█ █▀█ \n\
+                    █ █  █  █▀▀ ▀▀█ ▀▀█ ▀▀█ █▀█   █ █▀█ ▀▀█ \n\
+                    ▀▀▀  ▀  ▀▀▀ ▀▀▀   ▀ ▀▀▀ ▀▀▀   ▀ ▀▀▀ ▀▀▀ \n";
+    assert_eq!(buf[..len], *expected.as_bytes());
+
+    let content = draw_time_compact(12 * 3600 + 34 * 60 + 56, Config::default());
+    let width: usize = content.iter().map(|glyph| glyph.width()).sum();
+    assert_eq!(Style::Compact.size(true).0 as usize, width - 1);
+    let width: usize = content[..5].iter().map(|glyph| glyph.width()).sum();
+    assert_eq!(Style::Compact.size(false).0 as usize, width - 1);
+    let content = draw_stopwatch_compact(0);
+    let width: usize = content.iter().map(|glyph| glyph.width()).sum();
+    assert_eq!(Style::Compact.stopwatch_size().0 as usize, width - 1);
+}
+
+#[test]
+fn test_draw_binary() {
+    let mut buf = [0; 512];
+    let mut ctx = Context::new(io::ArrayWriter::new(&mut buf));
+    let content = draw_binary(12 * 3600 + 34 * 60 + 59, Config::default());
+    ctx.draw(None, None, || content).unwrap();
+    let len = ctx.writer.len;
+    let expected = "░░ ░░ ░░ ░░ ░░ ██ \n\
+                    ░░ ░░ ░░ ██ ██ ░░ \n\
+                    ░░ ██ ██ ░░ ░░ ░░ \n\
+                    ██ ░░ ██ ░░ ██ ██ \n";
+    assert_eq!(buf[..len], *expected.as_bytes());
+    assert_eq!(Style::Binary.size(true).0 as usize, content.len() * 3 - 1);
+
+    // at midnight every LED goes dark, each digit a different glyph than the second before
+    let before = draw_binary(24 * 3600 - 1, Config::default()).map(|g| g as *const Bits);
+    let midnight = draw_binary(0, Config::default()).map(|g| g as *const Bits);
+    assert!(before.iter().zip(&midnight).all(|(a, b)| a != b));
+    assert!(midnight.iter().all(|&g| core::ptr::eq(g, &BITS[0])));
+}
+
+#[test]
+fn test_draw_time_minimal() {
+    assert_eq!(draw_time_minimal(0, 0), *b"00:00");
+    assert_eq!(draw_time_minimal(13 * 3600 + 5 * 60 + 59, 0), *b"13:05");
+    assert_eq!(draw_time_minimal(3600, -2 * 3600), *b"23:00");
+    assert_eq!(draw_time_minimal(23 * 3600, 5 * 3600 + 1800), *b"04:30");
+}
+
+#[test]
+fn test_draw_time_iso8601() {
+    let t = 9 * 3600;
+    assert_eq!(draw_time_iso8601(t, 5 * 3600 + 30 * 60), *b"14:30:00+05:30");
+    assert_eq!(draw_time_iso8601(t + 59, 0), *b"09:00:59+00:00");
+    assert_eq!(draw_time_iso8601(t, -10 * 3600), *b"23:00:00-10:00");
+}
+
+#[test]
+fn test_decompose_elapsed() {
+    assert_eq!(decompose_elapsed(0), (0, 0, 0, 0));
+    assert_eq!(decompose_elapsed(86399), (0, 23, 59, 59));
+    assert_eq!(decompose_elapsed(3 * 86400 + 3723), (3, 1, 2, 3));
+    assert_eq!(draw_elapsed(42 * 86400 + 61), *b"042:00:01:01");
+    assert_eq!(draw_elapsed(5000 * 86400), *b"999:00:00:00");
+}
+
+#[test]
+fn test_draw_label_under() {
+    let render = |position, shadow, label| {
+        let mut buf = [0; 64];
+        let mut ctx = Context::new(io::ArrayWriter::new(&mut buf));
+        draw_label_under(&mut ctx, position, Config::default(), shadow, label).unwrap();
+        let len = ctx.writer.len;
+        (buf, len)
+    };
+    for (position, shadow, label, expected) in [
+        (
+            (1, 1),
+            false,
+            &b"UTC+08"[..],
+            &b"\x1b[7;1H\x1b[16CUTC+08\n"[..],
+        ),
+        (
+            (1, 1),
+            false,
+            b"UTC-03:30 ",
+            b"\x1b[7;1H\x1b[14CUTC-03:30 \n",
+        ),
+        ((4, 6), true, b"UTC+08", b"\x1b[11;6H\x1b[16CUTC+08\n"),
+    ] {
+        let (buf, len) = render(position, shadow, label);
+        assert_eq!(buf[..len], *expected);
+    }
+}
+
+#[test]
+fn test_draw_time_with_shadow() {
+    use color::{Color, Literal};
+    let mut buf = [0; 4096];
+    let mut ctx = Context::new(io::ArrayWriter::new(&mut buf));
+    let fg = color::Ink::Color(Color::Bright(Literal::Blue));
+    draw_time_with_shadow(
+        &mut ctx,
+        (1, 1),
+        0,
+        Config::default(),
+        Some(fg),
+        Some(b"AM"),
+    )
+    .unwrap();
+    let len = ctx.writer.len;
+    let clock = buf[..len]
+        .windows(11)
+        .position(|w| w == b"\x1b[22m\x1b[1;1H")
+        .unwrap();
+    // the shadow is the foreground, dimmed
+    assert_eq!(buf[..15], *b"\x1b[94m\x1b[2m\x1b[2;2H");
+    assert!(buf[..len].ends_with(b"AM"));
+    // the shadow draws its blanks, the clock skips over them
+    assert!(buf[..clock].contains(&b' '));
+    assert!(!buf[clock..len].contains(&b' '));
+}
+
+#[test]
+fn test_draw_date() {
+    assert_eq!(draw_date(0), *b"1970-01-01");
+    assert_eq!(draw_date(-1), *b"1969-12-31");
+    assert_eq!(draw_date(1_792_195_200 - 1), *b"2026-10-16");
+}
+
+#[test]
+fn test_content_width() {
+    assert_eq!(content_width(true), 39);
+    assert_eq!(content_width(false), 25);
+    assert_eq!(Style::Block.size(true).0, content_width(true));
+    for bits in 0..16 {
+        let config = Config {
+            format: match bits & 1 {
+                0 => ClockFormat::TwentyFourHour,
+                _ => ClockFormat::TwelveHour,
+            },
+            blink_colon: bits & 2 != 0,
+            show_seconds: bits & 4 != 0,
+            show_date: bits & 8 != 0,
+            ..Default::default()
+        };
+        let width = if config.show_seconds { 39 } else { 25 };
+        assert_eq!(config.content_width(), width);
+    }
+}
+
+#[test]
+fn test_draw_changed() {
+    let mut buf = [0; 4096];
+    let mut ctx = Context::new(io::ArrayWriter::new(&mut buf));
+    let frame = |ctx: &mut Context<io::ArrayWriter<4096>>, seconds| {
+        let start = ctx.writer.len;
+        let content = draw_time(seconds, Config::default());
+        ctx.draw(Some((3, 5)), None, || content).unwrap();
+        ctx.writer.len - start
+    };
+    let full = frame(&mut ctx, 12 * 3600 + 34 * 60 + 56);
+    let tick = frame(&mut ctx, 12 * 3600 + 34 * 60 + 57);
+    assert!(full > 400 && tick < 200, "{full} {tick}");
+    assert_eq!(frame(&mut ctx, 12 * 3600 + 34 * 60 + 57), 0);
+    ctx.invalidate();
+    assert_eq!(frame(&mut ctx, 12 * 3600 + 34 * 60 + 56), full);
+}
+
+#[test]
+fn test_config_default() {
+    let config = Config::default();
+    assert!(matches!(config.format, ClockFormat::TwentyFourHour));
+    assert!(!config.blink_colon && config.show_seconds && !config.show_date);
+    assert!(matches!(
+        config.fg_color,
+        color::Color::Bright(color::Literal::Blue)
+    ));
+    assert!(config.bg_color.is_none());
+    // 24-hour, with both colons lit on an odd second
+    let glyphs = draw_time(13 * 3600 + 4 * 60 + 5, config);
+    let expected = glyphs_for(b"13:04:05").unwrap();
+    assert!(
+        glyphs
+            .iter()
+            .zip(&expected[..])
+            .all(|(a, b)| core::ptr::eq(*a, *b))
+    );
+}
+
+#[test]
+fn test_blink_colon() {
+    let config = Config {
+        blink_colon: true,
+        ..Default::default()
+    };
+    for (seconds, colon) in [(0, true), (1, false), (12 * 3600 + 34 * 60 + 56, true)] {
+        let mut buf = [0; 128];
+        let mut ctx = Context::new(io::ArrayWriter::new(&mut buf));
+        let content = draw_time_braun_style(seconds, config);
+        ctx.draw(None, None, || content).unwrap();
+        let len = ctx.writer.len;
+        assert_eq!(buf[..len].contains(&b':'), colon);
+        assert_eq!(len, 3 * 15);
+    }
+}
+
+#[test]
+fn test_draw_scaled() {
+    let mut buf = [0; 128];
+    let mut ctx = Context::new(io::ArrayWriter::new(&mut buf));
+    ctx.set_scale(2);
+    let content = draw_time_braun_style(12 * 3600 + 34 * 60 + 56, Config::default());
+    ctx.draw(None, None, || content[..2].iter().copied())
+        .unwrap();
+    let len = ctx.writer.len;
+    assert_eq!(
+        buf[..len],
+        *b"  ||  --\\\\  \n  ||  --\\\\  \n  ||    //  \n  ||    //  \n  ||  //__  \n  ||  //__  \n"
+    );
+    // the label grows with the digits, over as many rows
+    let mut buf = [0; 128];
+    let mut ctx = Context::new(io::ArrayWriter::new(&mut buf));
+    ctx.set_scale(2);
+    let ink = color::Ink::Sgr(b"\x1b[94m");
+    let colors = |i| (i == LABEL).then_some(ink);
+    ctx.draw_colored(
+        None,
+        None,
+        || content[..1].iter().copied(),
+        colors,
+        Some(b"PM"),
+    )
+    .unwrap();
+    let len = ctx.writer.len;
+    assert_eq!(
+        buf[..len],
+        *b"  ||  \n  ||  \n  ||  \n  ||  \n  ||  \x1b[94mPPMM\n  ||  \x1b[94mPPMM\n"
+    );
+}
+
+#[test]
+fn test_draw_ascii() {
+    let mut buf = [0; 128];
+    let mut ctx = Context::new(io::ArrayWriter::new(&mut buf));
+    let content = draw_time(12 * 3600 + 34 * 60 + 56, Config::default()).map(Ascii::new);
+    ctx.draw(None, None, || content[..2].iter().copied())
+        .unwrap();
+    let len = ctx.writer.len;
+    assert_eq!(
+        buf[..len],
+        *b" ## ##### \n ##    ## \n ## ##### \n ## ##    \n ## ##### \n"
+    );
+}
+
+#[test]
+fn test_twelve_hour() {
+    for (h, expected) in [(0, 12), (1, 1), (11, 11), (12, 12), (13, 1), (23, 11)] {
+        let [_, _, h] = time(twelve_hour(h * 3600 + 59));
+        assert_eq!(h, expected);
+    }
+}
+
+#[test]
+fn test_clock_format() {
+    let format = ClockFormat::TwelveHour;
+    for (h, expected, label) in [(0, 12, b"AM"), (12, 12, b"PM"), (13, 1, b"PM")] {
+        let seconds = h * 3600 + 30 * 60;
+        assert_eq!(time(format.apply(seconds))[2], expected);
+        assert_eq!(format.label(seconds), Some(&label[..]));
+    }
+    assert_eq!(ClockFormat::TwentyFourHour.apply(13 * 3600), 13 * 3600);
+    assert_eq!(ClockFormat::TwentyFourHour.label(13 * 3600), None);
+}
+
+#[test]
+fn test_draw_status_bar() {
+    let mut buf = [0; 64];
+    let mut writer = io::ArrayWriter::new(&mut buf);
+    draw_status_bar(&mut writer, ClockMode::Wall, 1, -(5 * 3600 + 30 * 60), 80).unwrap();
+    let len = writer.len;
+    assert_eq!(buf[..len], *b"[WALL] TZ:-05:30 1fps\x1b[K");
+
+    let mut buf = [0; 64];
+    let mut writer = io::ArrayWriter::new(&mut buf);
+    draw_status_bar(&mut writer, ClockMode::Stopwatch, 30, 8 * 3600, 12).unwrap();
+    let len = writer.len;
+    assert_eq!(buf[..len], *b"[STOPWATCH] \x1b[K");
+}
+
+#[test]
+fn test_draw_time_utf8() {
+    // every digit in every place it can go, both colons, and AM as well as PM
+    const TIMES: [[isize; 3]; 10] = [
+        [0, 0, 0],
+        [11, 11, 11],
+        [22, 22, 22],
+        [3, 33, 33],
+        [14, 44, 44],
+        [5, 55, 55],
+        [16, 6, 6],
+        [7, 17, 17],
+        [18, 28, 28],
+        [9, 39, 39],
+    ];
+    let configs = [
+        Config::default(),
+        Config {
+            format: ClockFormat::TwelveHour,
+            ..Default::default()
+        },
+        Config {
+            blink_colon: true,
+            ..Default::default()
+        },
+    ];
+    // the faces that write more than one byte a cell, at each scale
+    for config in configs {
+        for [h, min, s] in TIMES {
+            let seconds = h * 3600 + min * 60 + s;
+            let label = config.format.label(seconds);
+            for scale in [1, 2] {
+                let mut buf = [0; 16384];
+                let mut ctx = Context::new(io::Utf8Validator::new(io::ArrayWriter::new(&mut buf)));
+                ctx.set_scale(scale);
+                let margin = Some(&b"\x1b[3C"[..]);
+                let content = draw_time(seconds, config);
+                ctx.draw_colored(None, margin, || content, |_| None, label)
+                    .unwrap();
+                ctx.draw(None, margin, || draw_time_compact(seconds, config))
+                    .unwrap();
+                ctx.draw(None, margin, || draw_binary(seconds, config))
+                    .unwrap();
+                draw_time_with_shadow(&mut ctx, (1, 1), seconds, config, None, label).unwrap();
+                ctx.writer.flush().unwrap();
+            }
+        }
+    }
+}
+
+#[test]
+fn test_glyphs_for() {
+    let render = |content: &[&'static DrawLineN]| {
+        let mut buf = [0; 512];
+        let mut ctx = Context::new(io::ArrayWriter::new(&mut buf));
+        ctx.draw(None, None, || content.iter().copied()).unwrap();
+        let len = ctx.writer.len;
+        (buf, len)
+    };
+    let config = Config {
+        blink_colon: false,
+        ..Default::default()
+    };
+    let (time, len) = render(&draw_time(12 * 3600 + 34 * 60 + 56, config)[..]);
+    let (text, text_len) = render(&glyphs_for(b"12:34:56").unwrap());
+    assert_eq!(time[..len], text[..text_len]);
+    assert_eq!(width_of(b"12:34:56"), Some(37));
+    assert_eq!(width_of(b"-1.5"), Some(15));
+    assert_eq!(width_of(b""), Some(0));
+    assert!(width_of(b"1a").is_none() && glyphs_for(b"1a").is_none());
+    assert!(glyphs_for(b"00:00:00.000").is_none());
+}
@@ -0,0 +1,194 @@
+/// Proleptic Gregorian, so the 400-year rule also applies before 1582.
+pub const fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+pub const fn days_in_year(year: i64) -> i64 {
+    if is_leap_year(year) { 366 } else { 365 }
+}
+
+/// `month` from 1 to 12.
+pub const fn days_in_month(year: i64, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days in each 400-year cycle, which starts over on the same weekday.
+const DAYS_PER_ERA: i64 = 146_097;
+/// From 0000-03-01, where the eras begin, to 1970-01-01.
+const EPOCH_DAYS: i64 = 719_468;
+
+/// Year, month and day of the month for `days` since 1970-01-01.
+pub const fn date(days: i64) -> (i64, u8, u8) {
+    // years run March to February here, so the leap day comes last
+    let days = days + EPOCH_DAYS;
+    let era = days.div_euclid(DAYS_PER_ERA);
+    let day_of_era = days.rem_euclid(DAYS_PER_ERA);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = era * 400 + year_of_era + (month <= 2) as i64;
+    (year, month as u8, day as u8)
+}
+
+/// Days since 1970-01-01 to `year`, `month` and `day`, the reverse of [`date`].
+pub const fn days(year: i64, month: u8, day: u8) -> i64 {
+    let year = year - (month <= 2) as i64;
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let day_of_year = (153 * month + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * DAYS_PER_ERA + day_of_era - EPOCH_DAYS
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    const ALL: [Self; 7] = [
+        Self::Monday,
+        Self::Tuesday,
+        Self::Wednesday,
+        Self::Thursday,
+        Self::Friday,
+        Self::Saturday,
+        Self::Sunday,
+    ];
+
+    /// The weekday `days` after 1970-01-01, a Thursday.
+    pub const fn from_days(days: i64) -> Self {
+        Self::ALL[(days + 3).rem_euclid(7) as usize]
+    }
+
+    pub const fn name(self) -> &'static [u8; 3] {
+        [b"Mon", b"Tue", b"Wed", b"Thu", b"Fri", b"Sat", b"Sun"][self as usize]
+    }
+}
+
+/// A point in UTC, broken down.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Date {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+    pub weekday: Weekday,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+/// Breaks down `secs` since the Unix epoch, before it if negative. The year wraps outside the
+/// range of `i32`.
+pub const fn from_unix(secs: i64) -> Date {
+    let days = secs.div_euclid(24 * 3600);
+    let secs = secs.rem_euclid(24 * 3600);
+    let (year, month, day) = date(days);
+    Date {
+        year: year as _,
+        month,
+        day,
+        weekday: Weekday::from_days(days),
+        hour: (secs / 3600) as _,
+        minute: (secs / 60 % 60) as _,
+        second: (secs % 60) as _,
+    }
+}
+
+#[test]
+fn test_leap_year() {
+    assert!(is_leap_year(2024) && is_leap_year(2000) && is_leap_year(1600));
+    assert!(!is_leap_year(2023) && !is_leap_year(1900) && !is_leap_year(2100));
+    assert_eq!(days_in_month(2024, 2), 29);
+    assert_eq!(days_in_month(1900, 2), 28);
+    assert_eq!(days_in_month(2023, 4), 30);
+    assert_eq!(days_in_month(2023, 12), 31);
+}
+
+#[test]
+fn test_date() {
+    assert_eq!(date(0), (1970, 1, 1));
+    assert_eq!(date(-1), (1969, 12, 31));
+    assert_eq!(date(59), (1970, 3, 1));
+    assert_eq!(date(11016), (2000, 2, 29));
+    assert_eq!(date(19782), (2024, 2, 29));
+    assert_eq!(date(20743), (2026, 10, 17));
+    assert_eq!(date(47481), (2099, 12, 31));
+    assert_eq!(date(-719_468), (0, 3, 1));
+    // every day follows the one before, across whole 400-year cycles either side of the epoch
+    let mut prev = date(-2 * DAYS_PER_ERA - 1);
+    for n in -2 * DAYS_PER_ERA..2 * DAYS_PER_ERA {
+        let (year, month, day) = date(n);
+        let (y, m, d) = prev;
+        let next = match (d == days_in_month(y, m), m) {
+            (false, _) => (y, m, d + 1),
+            (true, 12) => (y + 1, 1, 1),
+            (true, _) => (y, m + 1, 1),
+        };
+        assert_eq!((year, month, day), next);
+        assert_eq!(days(year, month, day), n);
+        prev = (year, month, day);
+    }
+}
+
+#[test]
+fn test_from_unix() {
+    let date = |year, month, day, weekday, (hour, minute, second)| Date {
+        year,
+        month,
+        day,
+        weekday,
+        hour,
+        minute,
+        second,
+    };
+    assert_eq!(from_unix(0), date(1970, 1, 1, Weekday::Thursday, (0, 0, 0)));
+    assert_eq!(
+        from_unix(951_782_400 + 12 * 3600 + 34 * 60 + 56),
+        date(2000, 2, 29, Weekday::Tuesday, (12, 34, 56))
+    );
+    assert_eq!(
+        from_unix(4_107_456_000),
+        date(2100, 2, 28, Weekday::Sunday, (0, 0, 0))
+    );
+    assert_eq!(
+        from_unix(4_107_456_000 + 24 * 3600),
+        date(2100, 3, 1, Weekday::Monday, (0, 0, 0))
+    );
+    assert_eq!(
+        from_unix(i32::MAX as _),
+        date(2038, 1, 19, Weekday::Tuesday, (3, 14, 7))
+    );
+    assert_eq!(
+        from_unix(-315_619_200),
+        date(1960, 1, 1, Weekday::Friday, (0, 0, 0))
+    );
+    assert_eq!(
+        from_unix(-1),
+        date(1969, 12, 31, Weekday::Wednesday, (23, 59, 59))
+    );
+    for (year, month, day) in [(i32::MAX, 12, 31), (i32::MIN, 1, 1)] {
+        let secs = days(year as _, month, day) * 24 * 3600;
+        let broken_down = from_unix(secs);
+        assert_eq!(
+            (broken_down.year, broken_down.month, broken_down.day),
+            (year, month, day)
+        );
+    }
+    assert_eq!(Weekday::from_days(-3).name(), b"Mon");
+}
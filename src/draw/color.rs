@@ -1,4 +1,7 @@
-use crate::{draw::COLOR_SEQUENCE_SISE, io};
+use crate::{
+    draw::COLOR_SEQUENCE_SISE,
+    io::{self, Write},
+};
 
 #[derive(Clone, Copy)]
 pub enum Literal {
@@ -91,3 +94,250 @@ impl Color {
         writer.len
     }
 }
+
+/// What the terminal is known to take, from `NO_COLOR` and `COLORTERM` at startup.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct ColorCaps {
+    /// `NO_COLOR` is set: the digits stay in the terminal's own foreground.
+    pub no_color: bool,
+    /// 24-bit colors go out as they are instead of as the nearest of 256.
+    pub truecolor: bool,
+}
+
+impl ColorCaps {
+    pub const fn color(self, color: Color) -> Option<Color> {
+        match color {
+            _ if self.no_color => None,
+            Color::Rgb { r, g, b } if !self.truecolor => Some(Color::Ansi(ansi256(r, g, b))),
+            color => Some(color),
+        }
+    }
+
+    /// Like [`Self::color`]; other SGR sequences such as dim aren't colors and pass.
+    pub const fn ink(self, ink: Ink) -> Option<Ink> {
+        match ink {
+            Ink::Color(color) => match self.color(color) {
+                Some(color) => Some(Ink::Color(color)),
+                None => None,
+            },
+            Ink::Sgr(_) => Some(ink),
+        }
+    }
+}
+
+/// The palette index closest to `r`, `g`, `b`, out of the 6x6x6 cube and the gray ramp.
+pub const fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    const fn cube_index(v: u8) -> u8 {
+        match v {
+            ..48 => 0,
+            48..115 => 1,
+            _ => (v - 35) / 40,
+        }
+    }
+    const fn cube_level(i: u8) -> u8 {
+        if i == 0 { 0 } else { 55 + i * 40 }
+    }
+    const fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+        let (dr, dg, db) = (
+            r1.abs_diff(r2) as u32,
+            g1.abs_diff(g2) as u32,
+            b1.abs_diff(b2) as u32,
+        );
+        dr * dr + dg * dg + db * db
+    }
+    let (ri, gi, bi) = (cube_index(r), cube_index(g), cube_index(b));
+    let cube = (cube_level(ri), cube_level(gi), cube_level(bi));
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray = match average {
+        ..3 => 0,
+        239.. => 23,
+        _ => (average - 3) / 10,
+    } as u8;
+    let level = 8 + gray * 10;
+    if distance((r, g, b), (level, level, level)) < distance((r, g, b), cube) {
+        232 + gray
+    } else {
+        16 + 36 * ri + 6 * gi + bi
+    }
+}
+
+pub fn write_fg(writer: &mut impl Write, color: Color) -> io::Result<()> {
+    let mut buf = [0; COLOR_SEQUENCE_SISE];
+    let len = color.ansi_sequence_fg(&mut buf);
+    writer.write_all(&buf[..len])
+}
+
+/// What a glyph is drawn in: a foreground color, or some other SGR sequence such as dim.
+#[derive(Clone, Copy)]
+pub enum Ink {
+    Color(Color),
+    Sgr(&'static [u8]),
+}
+
+pub fn write_ink(writer: &mut impl Write, ink: Ink) -> io::Result<()> {
+    match ink {
+        Ink::Color(color) => write_fg(writer, color),
+        Ink::Sgr(sgr) => writer.write_all(sgr),
+    }
+}
+
+const NAMES: [&[u8]; 8] = [
+    b"black", b"red", b"green", b"yellow", b"blue", b"magenta", b"cyan", b"white",
+];
+const LITERALS: [Literal; 8] = [
+    Literal::Black,
+    Literal::Red,
+    Literal::Green,
+    Literal::Yellow,
+    Literal::Blue,
+    Literal::Magenta,
+    Literal::Cyan,
+    Literal::White,
+];
+
+/// `h` in degrees, `s` and `v` out of 255.
+pub const fn hsv(h: u16, s: u8, v: u8) -> Color {
+    let (h, s, v) = (h as u32 % 360, s as u32, v as u32);
+    let f = h % 60 * 255 / 60;
+    let p = v * (255 - s) / 255;
+    let q = v * (255 - s * f / 255) / 255;
+    let t = v * (255 - s * (255 - f) / 255) / 255;
+    let (r, g, b) = match h / 60 {
+        0 => (v, t, p),
+        1 => (q, v, p),
+        2 => (p, v, t),
+        3 => (p, q, v),
+        4 => (t, p, v),
+        _ => (v, p, q),
+    };
+    Color::Rgb {
+        r: r as _,
+        g: g as _,
+        b: b as _,
+    }
+}
+
+/// The color of the `i`th glyph, turning once a minute, or once every six seconds through the
+/// bright colors without true color.
+pub const fn rainbow(seconds: isize, i: usize, truecolor: bool) -> Color {
+    if truecolor {
+        hsv(
+            ((seconds.rem_euclid(60) * 6) as usize + i * 45) as u16,
+            255,
+            255,
+        )
+    } else {
+        Color::Bright(LITERALS[1 + (seconds.rem_euclid(6) as usize + i) % 6])
+    }
+}
+
+fn parse_hex_byte(s: &[u8]) -> Option<u8> {
+    let digit = |c: u8| (c as char).to_digit(16);
+    match s {
+        &[hi, lo] => Some((digit(hi)? * 16 + digit(lo)?) as u8),
+        _ => None,
+    }
+}
+
+/// A name like `red` or `br_red`, a palette index as `colorNNN`, or `#RRGGBB`.
+pub fn parse(s: &[u8]) -> Option<Color> {
+    if let Some(hex) = s.strip_prefix(b"#") {
+        if hex.len() != 6 {
+            return None;
+        }
+        return Some(Color::Rgb {
+            r: parse_hex_byte(&hex[..2])?,
+            g: parse_hex_byte(&hex[2..4])?,
+            b: parse_hex_byte(&hex[4..])?,
+        });
+    }
+    if let Some(n) = s.strip_prefix(b"color") {
+        return match crate::args::parse_u64(n)? {
+            n @ 0..=255 => Some(Color::Ansi(n as _)),
+            _ => None,
+        };
+    }
+    let (name, bright) = match s.strip_prefix(b"br_") {
+        Some(name) => (name, true),
+        None => (s, false),
+    };
+    let literal = LITERALS[NAMES.iter().position(|&n| n == name)?];
+    Some(if bright {
+        Color::Bright(literal)
+    } else {
+        Color::Normal(literal)
+    })
+}
+
+#[test]
+fn test_parse_color() {
+    let mut buf = [0; 38];
+    let mut writer = io::ArrayWriter::new(&mut buf);
+    for s in [&b"br_blue"[..], b"red", b"color208", b"#FF8000"] {
+        write_fg(&mut writer, parse(s).unwrap()).unwrap();
+    }
+    assert_eq!(buf, *b"\x1b[94m\x1b[31m\x1b[38;5;208m\x1b[38;2;255;128;0m");
+    for s in [
+        &b"pink"[..],
+        b"br_",
+        b"color256",
+        b"color",
+        b"#ff800",
+        b"#gg0000",
+    ] {
+        assert!(parse(s).is_none());
+    }
+}
+
+#[test]
+fn test_color_caps() {
+    assert_eq!(ansi256(255, 128, 0), 208);
+    assert_eq!(ansi256(0, 0, 0), 16);
+    assert_eq!(ansi256(255, 255, 255), 231);
+    assert_eq!(ansi256(128, 128, 128), 244);
+    assert_eq!(ansi256(95, 135, 175), 67);
+    let orange = Color::Rgb {
+        r: 255,
+        g: 128,
+        b: 0,
+    };
+    let caps = ColorCaps::default();
+    assert!(matches!(caps.color(orange), Some(Color::Ansi(208))));
+    let caps = ColorCaps {
+        truecolor: true,
+        ..caps
+    };
+    assert!(matches!(caps.color(orange), Some(Color::Rgb { .. })));
+    let caps = ColorCaps {
+        no_color: true,
+        ..caps
+    };
+    assert!(caps.color(Color::Bright(Literal::Red)).is_none());
+    assert!(caps.ink(Ink::Color(orange)).is_none());
+    assert!(matches!(
+        caps.ink(Ink::Sgr(b"\x1b[2m")),
+        Some(Ink::Sgr(b"\x1b[2m"))
+    ));
+}
+
+#[test]
+fn test_hsv() {
+    let rgb = |h| match hsv(h, 255, 255) {
+        Color::Rgb { r, g, b } => (r, g, b),
+        _ => unreachable!(),
+    };
+    assert_eq!(rgb(0), (255, 0, 0));
+    assert_eq!(rgb(30), (255, 127, 0));
+    assert_eq!(rgb(60), (255, 255, 0));
+    assert_eq!(rgb(120), (0, 255, 0));
+    assert_eq!(rgb(240), (0, 0, 255));
+    assert_eq!(rgb(360), (255, 0, 0));
+    assert!(matches!(
+        hsv(200, 0, 128),
+        Color::Rgb {
+            r: 128,
+            g: 128,
+            b: 128
+        }
+    ));
+}
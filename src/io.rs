@@ -1,7 +1,55 @@
-use core::{fmt, slice};
+use core::{
+    fmt, slice,
+    sync::atomic::{AtomicBool, AtomicI32, Ordering},
+};
+
+use crate::io_uring::{Cqe as _, IoUring};
 
 pub type Result<T> = core::result::Result<T, nc::Errno>;
 
+pub const fn error_name(errno: nc::Errno) -> &'static str {
+    match errno {
+        nc::EPERM => "EPERM",
+        nc::ENOENT => "ENOENT",
+        nc::ESRCH => "ESRCH",
+        nc::EINTR => "EINTR",
+        nc::EIO => "EIO",
+        nc::ENXIO => "ENXIO",
+        nc::E2BIG => "E2BIG",
+        nc::EBADF => "EBADF",
+        nc::ECHILD => "ECHILD",
+        nc::EAGAIN => "EAGAIN",
+        nc::ENOMEM => "ENOMEM",
+        nc::EACCES => "EACCES",
+        nc::EFAULT => "EFAULT",
+        nc::EBUSY => "EBUSY",
+        nc::EEXIST => "EEXIST",
+        nc::ENODEV => "ENODEV",
+        nc::ENOTDIR => "ENOTDIR",
+        nc::EISDIR => "EISDIR",
+        nc::EINVAL => "EINVAL",
+        nc::ENFILE => "ENFILE",
+        nc::EMFILE => "EMFILE",
+        nc::ENOTTY => "ENOTTY",
+        nc::EFBIG => "EFBIG",
+        nc::ENOSPC => "ENOSPC",
+        nc::ESPIPE => "ESPIPE",
+        nc::EROFS => "EROFS",
+        nc::EPIPE => "EPIPE",
+        nc::ERANGE => "ERANGE",
+        nc::ENOSYS => "ENOSYS",
+        nc::ETIME => "ETIME",
+        nc::EOPNOTSUPP => "EOPNOTSUPP",
+        nc::ETIMEDOUT => "ETIMEDOUT",
+        nc::ECANCELED => "ECANCELED",
+        _ => "unknown error",
+    }
+}
+
+pub const fn error_description(errno: nc::Errno) -> &'static str {
+    nc::strerror(errno)
+}
+
 pub const trait Write: Sized {
     fn write(&mut self, bytes: &[u8]) -> Result<usize>;
     fn flush(&mut self) -> Result<usize>;
@@ -29,26 +77,194 @@ pub const trait Write: Sized {
             }
         }
     }
+
+    /// `n` right-aligned in at least `width` bytes, padded with `fill`; a wider `n` is
+    /// written in full.
+    fn write_u64_padded(&mut self, n: u64, width: usize, fill: u8) -> Result<usize> {
+        let (mut digits, mut rest) = (1, n / 10);
+        while rest != 0 {
+            digits += 1;
+            rest /= 10;
+        }
+        let fills = [fill; 20];
+        let mut pad = width.saturating_sub(digits);
+        let padded = pad;
+        while pad != 0 {
+            let len = if pad < fills.len() { pad } else { fills.len() };
+            if let Err(e) = self.write_all(unsafe { slice::from_raw_parts(fills.as_ptr(), len) }) {
+                return Err(e);
+            }
+            pad -= len;
+        }
+        match self.write_u64(n) {
+            Ok(len) => Ok(padded + len),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write_i64(&mut self, n: i64) -> Result<usize> {
+        if n < 0
+            && let Err(e) = self.write_all(b"-")
+        {
+            return Err(e);
+        }
+        match self.write_u64(n.unsigned_abs()) {
+            Ok(len) => Ok(len + (n < 0) as usize),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// `0x`-prefixed lowercase hex.
+    fn write_u64_hex(&mut self, n: u64) -> Result<usize> {
+        let mut buf = [0; 20];
+        let beg = format_hex(n, b"0123456789abcdef", &mut buf);
+        let hex = unsafe { slice::from_raw_parts(buf.as_ptr().add(beg), buf.len() - beg) };
+        match self.write_all(hex) {
+            Ok(_) => Ok(hex.len()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// `0x`-prefixed uppercase hex.
+    fn write_u64_hex_upper(&mut self, n: u64) -> Result<usize> {
+        let mut buf = [0; 20];
+        let beg = format_hex(n, b"0123456789ABCDEF", &mut buf);
+        let hex = unsafe { slice::from_raw_parts(buf.as_ptr().add(beg), buf.len() - beg) };
+        match self.write_all(hex) {
+            Ok(_) => Ok(hex.len()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// `format_args!` for any [`Write`]. Not part of it because `fmt::write` can't run in the
+/// const contexts [`Write`] is used in.
+pub trait WriteFmt: Write {
+    /// The writer's own error if it failed, `EIO` if the formatting did.
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<()> {
+        struct Adapter<'a, W: Write>(&'a mut W, Result<()>);
+        impl<W: Write> fmt::Write for Adapter<'_, W> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.0.write_all(s.as_bytes()).map_err(|e| {
+                    self.1 = Err(e);
+                    fmt::Error
+                })
+            }
+        }
+        let mut adapter = Adapter(self, Ok(()));
+        match fmt::write(&mut adapter, args) {
+            Ok(()) => Ok(()),
+            Err(_) => adapter.1.and(Err(nc::EIO)),
+        }
+    }
+}
+
+impl<W: Write> WriteFmt for W {}
+
+pub trait Read {
+    /// Reads what is there, at most `buf.len()` bytes; 0 at the end of the input.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// Fills all of `buf`, reading again after short reads; `EIO` if the input ends first.
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => return Err(nc::EIO),
+                n => buf = &mut buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads up to and including `delim` into `buf` and returns the length, shorter
+    /// without `delim` if the input ends or `buf` fills up first.
+    fn read_until(&mut self, delim: u8, buf: &mut [u8]) -> Result<usize> {
+        let mut len = 0;
+        while len < buf.len() {
+            match self.read(&mut buf[len..len + 1])? {
+                0 => break,
+                _ => len += 1,
+            }
+            if buf[len - 1] == delim {
+                break;
+            }
+        }
+        Ok(len)
+    }
+}
+
+/// Fills `buf` from the end and returns where the number starts.
+const fn format_hex(mut n: u64, digits: &[u8; 16], buf: &mut [u8; 20]) -> usize {
+    let mut beg = buf.len();
+    loop {
+        beg -= 1;
+        buf[beg] = digits[(n & 0xf) as usize];
+        n >>= 4;
+        if n == 0 {
+            break;
+        }
+    }
+    beg -= 2;
+    buf[beg] = b'0';
+    buf[beg + 1] = b'x';
+    beg
+}
+
+impl<W: Write> Write for &mut W {
+    fn write(&mut self, bytes: &[u8]) -> Result<usize> {
+        (**self).write(bytes)
+    }
+    fn flush(&mut self) -> Result<usize> {
+        (**self).flush()
+    }
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        (**self).write_all(bytes)
+    }
 }
 
 pub const STDIN: i32 = 0;
 pub const STDOUT: i32 = 1;
 pub const STDERR: i32 = 2;
 
+static ERROR_FD: AtomicI32 = AtomicI32::new(STDERR);
+
+static SYNC_ERRORS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_error_fd(fd: i32) {
+    ERROR_FD.store(fd, Ordering::Relaxed);
+}
+
+/// Makes [`SyncWriter::error`] sync what it writes.
+pub fn set_sync_errors(sync: bool) {
+    SYNC_ERRORS.store(sync, Ordering::Relaxed);
+}
+
 pub struct FdWriter(i32);
 #[derive(Clone, Copy)]
 pub struct FdReader(i32);
 
 impl FdWriter {
+    pub const fn new(fd: i32) -> Self {
+        Self(fd)
+    }
+    pub const fn fd(&self) -> i32 {
+        self.0
+    }
     pub const fn stdout() -> Self {
         Self(STDOUT)
     }
     pub const fn stderr() -> Self {
         Self(STDERR)
     }
+    pub fn error() -> Self {
+        Self(ERROR_FD.load(Ordering::Relaxed))
+    }
 }
 
 impl FdReader {
+    pub const fn new(fd: i32) -> Self {
+        Self(fd)
+    }
     pub const fn stdin() -> Self {
         Self(STDIN)
     }
@@ -56,6 +272,26 @@ impl FdReader {
     pub fn read(self, buf: &mut [u8]) -> Result<usize> {
         unsafe { nc::read(self.0, buf) }.map(|x| x as _)
     }
+
+    /// Reads only if data is ready right now, without touching the fd flags.
+    pub fn try_read(self, buf: &mut [u8]) -> Result<Option<usize>> {
+        let mut fds = [nc::pollfd_t {
+            fd: self.0,
+            events: nc::POLLIN,
+            revents: 0,
+        }];
+        let now = nc::timespec_t::default();
+        match unsafe { nc::ppoll(&mut fds, Some(&now), None) }? {
+            0 => Ok(None),
+            _ => self.read(buf).map(Some),
+        }
+    }
+}
+
+impl Read for FdReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        FdReader::read(*self, buf)
+    }
 }
 
 impl Write for FdWriter {
@@ -68,10 +304,63 @@ impl Write for FdWriter {
     fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
         let mut written = 0;
         while written < bytes.len() {
-            written += self.write(unsafe { bytes.get_unchecked(written..) })?;
+            match self.write(unsafe { bytes.get_unchecked(written..) }) {
+                Ok(n) => written += n,
+                // signal handlers are installed without SA_RESTART
+                Err(nc::EINTR) => {}
+                // someone sharing the terminal made it non-blocking
+                Err(nc::EAGAIN) => self.wait_writable()?,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FdWriter {
+    /// The slices in one `writev`, which like `write` may stop short.
+    pub fn write_vectored<const N: usize>(&mut self, slices: &[&[u8]; N]) -> Result<usize> {
+        const { assert!(N <= 8, "more slices than iovecs on the stack") };
+        let iovecs: [nc::iovec_t; N] = core::array::from_fn(|i| nc::iovec_t {
+            iov_base: slices[i].as_ptr() as _,
+            iov_len: slices[i].len(),
+        });
+        unsafe { nc::writev(self.0 as _, &iovecs) }.map(|n| n as _)
+    }
+
+    pub fn write_all_vectored<const N: usize>(&mut self, slices: &[&[u8]; N]) -> Result<()> {
+        let mut slices = *slices;
+        while slices.iter().any(|slice| !slice.is_empty()) {
+            let mut written = match self.write_vectored(&slices) {
+                Ok(n) => n,
+                Err(nc::EINTR) => continue,
+                Err(nc::EAGAIN) => {
+                    self.wait_writable()?;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            for slice in &mut slices {
+                let n = written.min(slice.len());
+                (*slice, written) = (&slice[n..], written - n);
+            }
         }
         Ok(())
     }
+
+    fn wait_writable(&self) -> Result<()> {
+        let mut fds = [nc::pollfd_t {
+            fd: self.0,
+            events: nc::POLLOUT,
+            revents: 0,
+        }];
+        loop {
+            match unsafe { nc::ppoll(&mut fds, None, None) } {
+                Err(nc::EINTR) => {}
+                result => return result.map(drop),
+            }
+        }
+    }
 }
 
 impl fmt::Write for FdWriter {
@@ -80,6 +369,33 @@ impl fmt::Write for FdWriter {
     }
 }
 
+/// An [`FdWriter`] whose `flush` waits for the data to reach the disk, leaving the metadata alone.
+pub struct SyncWriter(FdWriter);
+
+impl SyncWriter {
+    pub const fn new(writer: FdWriter) -> Self {
+        Self(writer)
+    }
+    /// The error fd, synced on flush only under `--sync-log`.
+    pub fn error() -> Option<Self> {
+        SYNC_ERRORS
+            .load(Ordering::Relaxed)
+            .then(|| Self(FdWriter::error()))
+    }
+}
+
+impl Write for SyncWriter {
+    fn write(&mut self, bytes: &[u8]) -> Result<usize> {
+        self.0.write(bytes)
+    }
+    fn flush(&mut self) -> Result<usize> {
+        unsafe { nc::fdatasync(self.0.0) }.map(|_| 0)
+    }
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        self.0.write_all(bytes)
+    }
+}
+
 pub struct BufWriter<Buffer: AsMut<[u8]>, Write: self::Write> {
     writer: Write,
     buffer: Buffer,
@@ -95,15 +411,29 @@ impl<Buffer: AsMut<[u8]>, Write: self::Write> BufWriter<Buffer, Write> {
         }
     }
 
+    /// Flushes and returns the inner writer. On failure the `BufWriter` comes back
+    /// with the error; its `offset` is unspecified, so buffered bytes may be lost.
+    pub fn into_inner(mut self) -> core::result::Result<Write, IntoInnerError<Self>> {
+        match self.flush() {
+            Ok(_) => Ok(self.writer),
+            Err(error) => Err(IntoInnerError(self, error)),
+        }
+    }
+
     pub fn flush(&mut self) -> Result<usize> {
         let n = self.offset;
-        self.offset = 0;
         self.writer
             .write_all(unsafe { &self.buffer.as_mut().get_unchecked(..n) })?;
+        self.offset = 0;
         Ok(n)
     }
 
+    fn remaining(&mut self) -> usize {
+        self.buffer.as_mut().len() - self.offset
+    }
+
     fn fill(&mut self, bytes: &[u8]) {
+        debug_assert!(bytes.len() <= self.remaining());
         unsafe {
             core::ptr::copy_nonoverlapping(
                 bytes.as_ptr(),
@@ -123,7 +453,7 @@ impl<Buffer: AsMut<[u8]>, Write: self::Write> BufWriter<Buffer, Write> {
             self.fill(bytes);
             return Ok(bytes.len());
         }
-        let remaining = self.buffer.as_mut().len() - self.offset;
+        let remaining = self.remaining();
         if bytes.len() <= remaining {
             self.fill(bytes);
             return Ok(bytes.len());
@@ -132,6 +462,18 @@ impl<Buffer: AsMut<[u8]>, Write: self::Write> BufWriter<Buffer, Write> {
         self.flush()?;
         self.write(unsafe { bytes.get_unchecked(remaining..) })
     }
+
+    /// Fails with `ENOBUFS` if `bytes` don't fit and flushing couldn't make room.
+    pub fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        if bytes.len() > self.remaining() && self.flush().is_err() {
+            return Err(nc::ENOBUFS);
+        }
+        if bytes.len() > self.remaining() {
+            return self.writer.write_all(bytes);
+        }
+        self.fill(bytes);
+        Ok(())
+    }
 }
 
 impl<Buffer: AsMut<[u8]>, Write: self::Write> self::Write for BufWriter<Buffer, Write> {
@@ -142,7 +484,244 @@ impl<Buffer: AsMut<[u8]>, Write: self::Write> self::Write for BufWriter<Buffer,
         self.flush()
     }
     fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
-        self.write(bytes).map(|_| ())
+        self.write_all(bytes)
+    }
+}
+
+/// Each piece goes in whole or not at all, so a failed `write!` leaves the buffer holding
+/// exactly the pieces before the one that failed.
+impl<Buffer: AsMut<[u8]>, Write: self::Write> fmt::Write for BufWriter<Buffer, Write> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+/// Reads `Read` in chunks the size of `buffer`, handing them out from the window
+/// `offset..len` until it runs dry.
+pub struct BufReader<Buffer: AsMut<[u8]>, Read: self::Read> {
+    reader: Read,
+    buffer: Buffer,
+    offset: usize,
+    len: usize,
+}
+
+impl<Buffer: AsMut<[u8]>, Read: self::Read> BufReader<Buffer, Read> {
+    pub const fn new(reader: Read, buffer: Buffer) -> Self {
+        Self {
+            reader,
+            buffer,
+            offset: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the inner reader; whatever is still buffered is lost.
+    pub fn into_inner(self) -> Read {
+        self.reader
+    }
+
+    /// The buffered bytes, reading more first if there are none; empty at the end of the input.
+    pub fn fill_buf(&mut self) -> Result<&[u8]> {
+        if self.offset == self.len {
+            self.len = self.reader.read(self.buffer.as_mut())?;
+            self.offset = 0;
+        }
+        Ok(&self.buffer.as_mut()[self.offset..self.len])
+    }
+
+    pub fn consume(&mut self, n: usize) {
+        self.offset = (self.offset + n).min(self.len);
+    }
+}
+
+impl<Buffer: AsMut<[u8]>, Read: self::Read> self::Read for BufReader<Buffer, Read> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        // nothing buffered and a read at least as big as the buffer: skip the copy
+        if self.offset == self.len && buf.len() >= self.buffer.as_mut().len() {
+            return self.reader.read(buf);
+        }
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+
+    fn read_until(&mut self, delim: u8, buf: &mut [u8]) -> Result<usize> {
+        let mut len = 0;
+        while len < buf.len() {
+            let available = self.fill_buf()?;
+            if available.is_empty() {
+                break;
+            }
+            let room = available.len().min(buf.len() - len);
+            let (n, found) = match available[..room].iter().position(|&c| c == delim) {
+                Some(i) => (i + 1, true),
+                None => (room, false),
+            };
+            buf[len..len + n].copy_from_slice(&available[..n]);
+            self.consume(n);
+            len += n;
+            if found {
+                break;
+            }
+        }
+        Ok(len)
+    }
+}
+
+pub struct IntoInnerError<W>(W, nc::Errno);
+
+impl<W> IntoInnerError<W> {
+    pub const fn error(&self) -> nc::Errno {
+        self.1
+    }
+
+    pub fn into_inner(self) -> W {
+        self.0
+    }
+}
+
+/// Double-buffered writer that hands full buffers to the ring with `IORING_OP_WRITE`.
+/// At most one buffer is in flight; its completion must be passed to [`UringWriter::complete`].
+pub struct UringWriter<'a> {
+    ring: &'a IoUring,
+    fd: i32,
+    user_data: usize,
+    buffers: [&'a mut [u8]; 2],
+    current: usize,
+    len: usize,
+    // (written, len) of the other buffer
+    in_flight: Option<(usize, usize)>,
+    pending: bool,
+}
+
+impl<'a> UringWriter<'a> {
+    pub const fn new(
+        ring: &'a IoUring,
+        fd: i32,
+        user_data: usize,
+        buffers: [&'a mut [u8]; 2],
+    ) -> Self {
+        Self {
+            ring,
+            fd,
+            user_data,
+            buffers,
+            current: 0,
+            len: 0,
+            in_flight: None,
+            pending: false,
+        }
+    }
+
+    pub const fn is_idle(&self) -> bool {
+        self.in_flight.is_none() && self.len == 0
+    }
+
+    fn submit(&mut self) -> Result<()> {
+        let (index, len) = (self.current, self.len);
+        self.current ^= 1;
+        self.len = 0;
+        self.pending = false;
+        self.in_flight = Some((0, len));
+        let buf = &self.buffers[index][..len];
+        self.ring
+            .submit_batch(|ring| ring.prepare_write(self.fd as _, buf, self.user_data))?;
+        Ok(())
+    }
+
+    pub fn complete(&mut self, cqe: &nc::io_uring_cqe_t) -> Result<()> {
+        let Some((written, len)) = self.in_flight else {
+            return Err(nc::EIO);
+        };
+        let written = written + cqe.result()? as usize;
+        if written < len {
+            self.in_flight = Some((written, len));
+            let rest = &self.buffers[self.current ^ 1][written..len];
+            self.ring
+                .submit_batch(|ring| ring.prepare_write(self.fd as _, rest, self.user_data))?;
+            return Ok(());
+        }
+        self.in_flight = None;
+        if self.pending {
+            self.submit()?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for UringWriter<'_> {
+    fn write(&mut self, bytes: &[u8]) -> Result<usize> {
+        let buffer = &mut self.buffers[self.current];
+        let n = bytes.len().min(buffer.len() - self.len);
+        if n == 0 && !bytes.is_empty() {
+            // with both buffers taken, the older one has to land before this one can go
+            while self.in_flight.is_some() {
+                let cqe = self.ring.take_completion(self.user_data)?;
+                self.complete(&cqe)?;
+            }
+            if self.len != 0 {
+                self.submit()?;
+            }
+            return self.write(bytes);
+        }
+        buffer[self.len..self.len + n].copy_from_slice(&bytes[..n]);
+        self.len += n;
+        Ok(n)
+    }
+    fn flush(&mut self) -> Result<usize> {
+        let n = self.len;
+        if n == 0 {
+            return Ok(0);
+        }
+        if self.in_flight.is_some() {
+            self.pending = true;
+        } else {
+            self.submit()?;
+        }
+        Ok(n)
+    }
+    fn write_all(&mut self, mut bytes: &[u8]) -> Result<()> {
+        while !bytes.is_empty() {
+            let n = self.write(bytes)?;
+            bytes = &bytes[n..];
+        }
+        Ok(())
+    }
+}
+
+/// Passes through at most `limit` bytes and silently drops the rest.
+pub struct LimitWriter<W: Write> {
+    inner: W,
+    remaining: usize,
+}
+
+impl<W: Write> LimitWriter<W> {
+    pub const fn new(inner: W, limit: usize) -> Self {
+        Self {
+            inner,
+            remaining: limit,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for LimitWriter<W> {
+    fn write(&mut self, bytes: &[u8]) -> Result<usize> {
+        self.write_all(bytes)?;
+        Ok(bytes.len())
+    }
+    fn flush(&mut self) -> Result<usize> {
+        self.inner.flush()
+    }
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        let n = bytes.len().min(self.remaining);
+        self.remaining -= n;
+        self.inner.write_all(&bytes[..n])
     }
 }
 
@@ -151,18 +730,27 @@ pub struct ArrayWriter<'a, const N: usize> {
     pub len: usize,
 }
 
+/// Writes what fits; `write_all` fails with `ENOSPC` and writes nothing if not all of it does.
 impl<const N: usize> const Write for ArrayWriter<'_, N> {
     fn write(&mut self, bytes: &[u8]) -> Result<usize> {
-        unsafe { self.write_bytes_unchecked(bytes) };
-        Ok(bytes.len())
+        let n = if bytes.len() < N - self.len {
+            bytes.len()
+        } else {
+            N - self.len
+        };
+        unsafe { self.write_bytes_unchecked(slice::from_raw_parts(bytes.as_ptr(), n)) };
+        Ok(n)
     }
 
     fn flush(&mut self) -> Result<usize> {
-        unimplemented!()
+        Ok(0)
     }
 
     fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
-        _ = self.write(bytes);
+        if bytes.len() > N - self.len {
+            return Err(nc::ENOSPC);
+        }
+        unsafe { self.write_bytes_unchecked(bytes) };
         Ok(())
     }
 }
@@ -188,6 +776,432 @@ impl<'a, const N: usize> ArrayWriter<'a, N> {
     pub const unsafe fn write_u64_unchecked(&mut self, n: u64) {
         _ = self.write_u64(n);
     }
+    /// # Safety
+    ///
+    /// The padded number has to fit in what is left of the buffer.
+    pub const unsafe fn write_u64_padded_unchecked(&mut self, n: u64, width: usize, fill: u8) {
+        _ = self.write_u64_padded(n, width, fill);
+    }
+}
+
+pub struct Utf8Validator<W: Write> {
+    inner: W,
+    leftover: [u8; 3],
+    leftover_len: u8,
+}
+
+const fn utf8_width(byte: u8) -> u8 {
+    match byte {
+        0x00..=0x7f => 1,
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => 0,
+    }
+}
+
+impl<W: Write> Utf8Validator<W> {
+    pub const fn new(inner: W) -> Self {
+        Self {
+            inner,
+            leftover: [0; 3],
+            leftover_len: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn validate(&mut self, bytes: &[u8]) -> Result<()> {
+        for &byte in bytes {
+            if self.leftover_len == 0 {
+                match utf8_width(byte) {
+                    0 => return Err(nc::EILSEQ),
+                    1 => {}
+                    _ => {
+                        self.leftover[0] = byte;
+                        self.leftover_len = 1;
+                    }
+                }
+                continue;
+            }
+            let first = self.leftover[0];
+            let range = match (self.leftover_len, first) {
+                (1, 0xe0) => 0xa0..=0xbf,
+                (1, 0xed) => 0x80..=0x9f,
+                (1, 0xf0) => 0x90..=0xbf,
+                (1, 0xf4) => 0x80..=0x8f,
+                _ => 0x80..=0xbf,
+            };
+            if !range.contains(&byte) {
+                self.leftover_len = 0;
+                return Err(nc::EILSEQ);
+            }
+            if self.leftover_len + 1 == utf8_width(first) {
+                self.leftover_len = 0;
+            } else {
+                self.leftover[self.leftover_len as usize] = byte;
+                self.leftover_len += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Utf8Validator<W> {
+    fn write(&mut self, bytes: &[u8]) -> Result<usize> {
+        self.validate(bytes)?;
+        self.inner.write(bytes)
+    }
+    fn flush(&mut self) -> Result<usize> {
+        if self.leftover_len != 0 {
+            return Err(nc::EILSEQ);
+        }
+        self.inner.flush()
+    }
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        self.validate(bytes)?;
+        self.inner.write_all(bytes)
+    }
+}
+
+/// A copy of the process with only stdio and `keep` left open, so it can't hold on to the pipes
+/// of tests running on other threads. `Ok(0)` in the copy, its pid in the caller.
+///
+/// # Safety
+/// The copy has only the calling thread, and must leave through `exit_group`.
+pub unsafe fn fork_keeping(keep: i32) -> Result<i32> {
+    let child = unsafe { nc::clone(nc::SIGCHLD as _, core::ptr::null(), None, None, None) }?;
+    if child == 0 {
+        let keep = keep as u32;
+        let below = match keep {
+            4.. => unsafe { nc::close_range(3, keep - 1, 0) },
+            _ => Ok(()),
+        };
+        if below
+            .and_then(|()| unsafe { nc::close_range(keep + 1, u32::MAX, 0) })
+            .is_err()
+        {
+            unsafe { nc::exit_group(2) };
+        }
+    }
+    Ok(child)
+}
+
+#[test]
+fn test_utf8_validator() {
+    let mut buf = [0; 16];
+    let mut writer = Utf8Validator::new(ArrayWriter::new(&mut buf));
+    let block = "█".as_bytes();
+    writer.write_all(b"ok ").unwrap();
+    writer.write_all(&block[..1]).unwrap();
+    assert_eq!(writer.flush(), Err(nc::EILSEQ));
+    writer.write_all(&block[1..]).unwrap();
+    assert_eq!(writer.write_all(b"\xed\xa0\x80"), Err(nc::EILSEQ));
+    assert_eq!(writer.write_all(b"\xc0\xaf"), Err(nc::EILSEQ));
+    assert_eq!(writer.write_all(b"\xe2\x41"), Err(nc::EILSEQ));
+    assert_eq!(writer.into_inner().len, 6);
+}
+
+#[test]
+fn test_fd_reader() {
+    let mut fds = [0; 2];
+    unsafe { nc::pipe2(&mut fds, nc::O_CLOEXEC) }.unwrap();
+    let (mut reader, mut writer) = (FdReader::new(fds[0]), FdWriter::new(fds[1]));
+    let mut buf = [0; 8];
+    assert_eq!(reader.try_read(&mut buf), Ok(None));
+
+    writer.write_all(b"hello").unwrap();
+    assert_eq!(reader.try_read(&mut buf), Ok(Some(5)));
+    assert_eq!(buf[..5], *b"hello");
+
+    writer.write_all(b"abcdefgh").unwrap();
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, *b"abcdefgh");
+
+    writer.write_all(b"xy").unwrap();
+    unsafe { nc::close(fds[1]) }.unwrap();
+    assert_eq!(reader.read_exact(&mut buf), Err(nc::EIO));
+    unsafe { nc::close(fds[0]) }.unwrap();
+}
+
+#[test]
+fn test_buf_reader() {
+    let mut fds = [0; 2];
+    unsafe { nc::pipe2(&mut fds, nc::O_CLOEXEC) }.unwrap();
+    let mut writer = FdWriter::new(fds[1]);
+    writer.write_all(b"TZif2\nheader\n0123456789").unwrap();
+    let mut reader = BufReader::new(FdReader::new(fds[0]), [0u8; 4]);
+    let mut line = [0; 16];
+    let len = reader.read_until(b'\n', &mut line).unwrap();
+    assert_eq!(line[..len], *b"TZif2\n");
+    let len = reader.read_until(b'\n', &mut line[..3]).unwrap();
+    assert_eq!(line[..len], *b"hea");
+    let len = reader.read_until(b'\n', &mut line).unwrap();
+    assert_eq!(line[..len], *b"der\n");
+    let mut buf = [0; 6];
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, *b"012345");
+
+    unsafe { nc::close(fds[1]) }.unwrap();
+    assert_eq!(reader.read_exact(&mut buf), Err(nc::EIO));
+    assert_eq!(reader.read_until(b'\n', &mut line), Ok(0));
+    unsafe { nc::close(fds[0]) }.unwrap();
+}
+
+#[test]
+fn test_write_vectored() {
+    let mut fds = [0; 2];
+    unsafe { nc::pipe2(&mut fds, nc::O_CLOEXEC) }.unwrap();
+    let mut writer = FdWriter::new(fds[1]);
+    assert_eq!(writer.write_vectored(&[&b"ab"[..], b"", b"cde"]), Ok(5));
+    assert_eq!(writer.write_vectored(&[]), Ok(0));
+    // more than the pipe takes at once, read back on the other end as it fills
+    unsafe { nc::fcntl(fds[1], nc::F_SETPIPE_SZ, 4096 as _) }.unwrap();
+    let (head, tail) = ([b'x'; 3000], [b'y'; 3000]);
+    let child = unsafe { fork_keeping(fds[1]) };
+    if child.unwrap() == 0 {
+        let mut writer = FdWriter::new(fds[1]);
+        let result = writer.write_all_vectored(&[&head[..], b"-", &tail]);
+        unsafe { nc::exit_group(result.is_err() as _) };
+    }
+    unsafe { nc::close(fds[1]) }.unwrap();
+    let mut buf = [0; 8192];
+    let mut len = 0;
+    while let n @ 1.. = FdReader::new(fds[0]).read(&mut buf[len..]).unwrap() {
+        len += n;
+    }
+    assert_eq!(buf[..5], *b"abcde");
+    assert_eq!(len, 5 + 6001);
+    assert!(buf[5..3005].iter().all(|&c| c == b'x'));
+    assert_eq!(buf[3005], b'-');
+    assert!(buf[3006..len].iter().all(|&c| c == b'y'));
+    let mut status = 0;
+    unsafe { nc::wait4(child.unwrap(), Some(&mut status), 0, None) }.unwrap();
+    assert_eq!(status, 0);
+    unsafe { nc::close(fds[0]) }.unwrap();
+}
+
+#[test]
+fn test_fd_writer_retries() {
+    extern "C" fn ignore(_: i32) {}
+    let sa = nc::sigaction_t {
+        sa_handler: ignore as *const () as _,
+        sa_flags: nc::SA_RESTORER,
+        sa_restorer: nc::restore::get_sa_restorer(),
+        ..Default::default()
+    };
+    unsafe { nc::rt_sigaction(nc::SIGALRM, Some(&sa), None) }.unwrap();
+    let mut fds = [0; 2];
+    unsafe { nc::pipe2(&mut fds, nc::O_CLOEXEC) }.unwrap();
+    let (pid, tid) = unsafe { (nc::getpid(), nc::gettid()) };
+    // a slow reader that keeps interrupting the writer
+    let child = unsafe { fork_keeping(fds[0]) }.unwrap();
+    if child == 0 {
+        let pause = nc::timespec_t {
+            tv_sec: 0,
+            tv_nsec: 1_000_000,
+        };
+        let mut buf = [0; 4096];
+        loop {
+            unsafe {
+                _ = nc::tgkill(pid, tid, nc::SIGALRM);
+                _ = nc::nanosleep(&pause, None);
+                if nc::read(fds[0], &mut buf) != Ok(buf.len() as _) {
+                    nc::exit_group(0);
+                }
+            }
+        }
+    }
+    unsafe { nc::close(fds[0]) }.unwrap();
+    unsafe { nc::fcntl(fds[1], nc::F_SETFL, nc::O_NONBLOCK as _) }.unwrap();
+    let data = [b'x'; 256 * 1024];
+    assert_eq!(FdWriter::new(fds[1]).write_all(&data), Ok(()));
+    unsafe { nc::close(fds[1]) }.unwrap();
+    let mut status = 0;
+    while unsafe { nc::wait4(child, Some(&mut status), 0, None) } == Err(nc::EINTR) {}
+    let sa = nc::sigaction_t::default();
+    unsafe { nc::rt_sigaction(nc::SIGALRM, Some(&sa), None) }.unwrap();
+}
+
+#[test]
+fn test_error_name() {
+    assert_eq!(error_name(nc::ENOENT), "ENOENT");
+    assert_eq!(error_name(nc::ENOTTY), "ENOTTY");
+    assert_eq!(error_name(nc::ENOSPC), "ENOSPC");
+    assert_eq!(error_name(4095), "unknown error");
+    assert_eq!(error_description(nc::EINTR), "Interrupted system call");
+}
+
+#[test]
+fn test_sync_writer() {
+    let mut fds = [0; 2];
+    unsafe { nc::pipe2(&mut fds, nc::O_CLOEXEC) }.unwrap();
+    let mut writer = SyncWriter::new(FdWriter::new(fds[1]));
+    writer.write_all(b"hello").unwrap();
+    // pipes have nothing to sync, which shows `flush` reached `fdatasync`
+    assert_eq!(writer.flush(), Err(nc::EINVAL));
+    let mut buf = [0; 5];
+    FdReader::new(fds[0]).read_exact(&mut buf).unwrap();
+    assert_eq!(buf, *b"hello");
+    unsafe { nc::close(fds[0]) }.unwrap();
+    unsafe { nc::close(fds[1]) }.unwrap();
+}
+
+#[test]
+fn test_buf_writer_into_inner() {
+    let mut buf = [0; 16];
+    let mut writer = BufWriter::new(ArrayWriter::new(&mut buf), [0u8; 64]);
+    writer.write_all(b"hello").unwrap();
+    let Ok(inner) = writer.into_inner() else {
+        panic!("flush failed");
+    };
+    assert_eq!(inner.len, 5);
+    assert_eq!(buf[..5], *b"hello");
+}
+
+#[test]
+fn test_buf_writer_overflow() {
+    let mut writer = BufWriter::new(FdWriter::new(-1), [0u8; 8]);
+    writer.write_all(b"hello").unwrap();
+    assert_eq!(writer.write_all(b"world"), Err(nc::ENOBUFS));
+    assert_eq!(writer.offset, 5);
+    writer.write_all(b"!!!").unwrap();
+    assert_eq!(writer.flush(), Err(nc::EBADF));
+}
+
+#[test]
+fn test_write_u64() {
+    for (n, expected) in [
+        (0, &b"0"[..]),
+        (9, b"9"),
+        (10, b"10"),
+        (99, b"99"),
+        (100, b"100"),
+        (1234567890, b"1234567890"),
+        (u64::MAX, b"18446744073709551615"),
+    ] {
+        let mut buf = [0; 20];
+        let mut writer = ArrayWriter::new(&mut buf);
+        assert_eq!(writer.write_u64(n), Ok(expected.len()));
+        let len = writer.len;
+        assert_eq!(buf[..len], *expected);
+    }
+}
+
+#[test]
+fn test_array_writer_full() {
+    let mut buf = [0; 4];
+    let mut writer = ArrayWriter::new(&mut buf);
+    assert_eq!(writer.write_all(b"hello"), Err(nc::ENOSPC));
+    assert_eq!(writer.len, 0);
+    assert_eq!(writer.write(b"hello"), Ok(4));
+    assert_eq!(writer.write(b"!"), Ok(0));
+    assert_eq!(writer.write_u64(7), Err(nc::ENOSPC));
+    assert_eq!(writer.len, 4);
+    assert_eq!(buf, *b"hell");
+}
+
+#[test]
+fn test_write_u64_hex() {
+    for (n, lower, upper) in [
+        (0, &b"0x0"[..], &b"0x0"[..]),
+        (u64::MAX, b"0xffffffffffffffff", b"0xFFFFFFFFFFFFFFFF"),
+        (0xdeadbeef, b"0xdeadbeef", b"0xDEADBEEF"),
+    ] {
+        let mut buf = [0; 40];
+        let mut writer = ArrayWriter::new(&mut buf);
+        assert_eq!(writer.write_u64_hex(n), Ok(lower.len()));
+        assert_eq!(writer.write_u64_hex_upper(n), Ok(upper.len()));
+        let len = writer.len;
+        assert_eq!(buf[..lower.len()], *lower);
+        assert_eq!(buf[lower.len()..len], *upper);
+    }
+}
+
+#[test]
+fn test_write_u64_padded() {
+    for (n, width, fill, expected) in [
+        (7, 2, b'0', &b"07"[..]),
+        (7, 0, b'0', b"7"),
+        (0, 0, b'0', b"0"),
+        (42, 2, b'0', b"42"),
+        (2024, 2, b'0', b"2024"),
+        (5, 4, b' ', b"   5"),
+        (1, 25, b'0', b"0000000000000000000000001"),
+        (u64::MAX, 20, b'0', b"18446744073709551615"),
+        (u64::MAX, 22, b'0', b"0018446744073709551615"),
+    ] {
+        let mut buf = [0; 32];
+        let mut writer = ArrayWriter::new(&mut buf);
+        assert_eq!(writer.write_u64_padded(n, width, fill), Ok(expected.len()));
+        let len = writer.len;
+        assert_eq!(buf[..len], *expected);
+    }
+    let mut buf = [0; 48];
+    let mut writer = ArrayWriter::new(&mut buf);
+    assert_eq!(writer.write_i64(-42), Ok(3));
+    assert_eq!(writer.write_i64(0), Ok(1));
+    assert_eq!(writer.write_i64(i64::MIN), Ok(20));
+    let len = writer.len;
+    assert_eq!(buf[..len], *b"-420-9223372036854775808");
+
+    const PADDED: ([u8; 8], usize) = {
+        let mut buf = [0; 8];
+        let mut writer = ArrayWriter::new(&mut buf);
+        unsafe { writer.write_u64_padded_unchecked(9, 4, b'0') };
+        let len = writer.len;
+        (buf, len)
+    };
+    assert_eq!(PADDED.0[..PADDED.1], *b"0009");
+}
+
+#[test]
+fn test_buf_writer_fmt() {
+    struct Counting<'a>(ArrayWriter<'a, 64>, usize);
+    impl Write for Counting<'_> {
+        fn write(&mut self, bytes: &[u8]) -> Result<usize> {
+            self.write_all(bytes).map(|_| bytes.len())
+        }
+        fn flush(&mut self) -> Result<usize> {
+            Ok(0)
+        }
+        fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+            self.1 += 1;
+            self.0.write_all(bytes)
+        }
+    }
+    let mut buf = [0; 64];
+    let mut writer = BufWriter::new(Counting(ArrayWriter::new(&mut buf), 0), [0u8; 32]);
+    let (h, min) = (7, 5);
+    fmt::Write::write_fmt(&mut writer, format_args!("{h:02}:{min:02} {}", "UTC")).unwrap();
+    let Ok(Counting(inner, writes)) = writer.into_inner() else {
+        panic!("flush failed");
+    };
+    assert_eq!(writes, 1);
+    let len = inner.len;
+    assert_eq!(buf[..len], *b"07:05 UTC");
+}
+
+#[test]
+fn test_write_fmt() {
+    struct Fails;
+    impl fmt::Display for Fails {
+        fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+            Err(fmt::Error)
+        }
+    }
+    let mut buf = [0; 8];
+    let mut writer = ArrayWriter::new(&mut buf);
+    WriteFmt::write_fmt(&mut writer, format_args!("{}:{:02}", 1, 5)).unwrap();
+    assert_eq!(writer.len, 4);
+    let result = WriteFmt::write_fmt(&mut writer, format_args!("{}", "too long"));
+    assert_eq!(result, Err(nc::ENOSPC));
+    let result = WriteFmt::write_fmt(&mut writer, format_args!("{}", Fails));
+    assert_eq!(result, Err(nc::EIO));
+    let len = writer.len;
+    assert_eq!(buf[..len], *b"1:05");
 }
 
 #[test]
@@ -198,8 +1212,56 @@ fn test_copy() {
     assert_eq!(dst[..src.len()], src[..])
 }
 
-//impl<Buffer: AsMut<[u8]>, Write: self::Write> fmt::Write for BufWriter<Buffer, Write> {
-//    fn write_str(&mut self, s: &str) -> fmt::Result {
-//        self.write(s.as_bytes()).map(|_| ()).map_err(|_| fmt::Error)
-//    }
-//}
+#[test]
+fn test_uring_writer_waits() {
+    let ring = IoUring::new(4).unwrap();
+    let mut fds = [0; 2];
+    unsafe { nc::pipe2(&mut fds, nc::O_CLOEXEC) }.unwrap();
+    // a completion of something else, queued ahead of the writes
+    ring.prepare_timeout(&nc::timespec_t::default(), 9, 0)
+        .unwrap();
+    ring.submit_wait(1).unwrap();
+    let (mut front, mut back) = ([0; 4], [0; 4]);
+    let mut writer = UringWriter::new(&ring, fds[1], 7, [&mut front, &mut back]);
+    writer.write_all(b"abcd").unwrap();
+    writer.flush().unwrap();
+    writer.write_all(b"efghij").unwrap();
+    writer.flush().unwrap();
+    assert_eq!(ring.complete().map(|cqe| cqe.user_data), Some(9));
+    while !writer.is_idle() {
+        let cqe = ring.take_completion(7).unwrap();
+        writer.complete(&cqe).unwrap();
+    }
+    let mut buf = [0; 16];
+    let n = unsafe { nc::read(fds[0], &mut buf) }.unwrap() as usize;
+    assert_eq!(buf[..n], *b"abcdefghij");
+    for fd in fds {
+        unsafe { nc::close(fd) }.unwrap();
+    }
+}
+
+#[test]
+fn test_uring_writer_behind_queued() {
+    let ring = IoUring::new(4).unwrap();
+    let mut fds = [0; 2];
+    unsafe { nc::pipe2(&mut fds, nc::O_CLOEXEC) }.unwrap();
+    // queued and not submitted, so the kernel takes it before any write
+    ring.prepare_timeout(&nc::timespec_t::default(), 9, 0)
+        .unwrap();
+    let (mut front, mut back) = ([0; 4], [0; 4]);
+    let mut writer = UringWriter::new(&ring, fds[1], 7, [&mut front, &mut back]);
+    writer.write_all(b"abcdefghij").unwrap();
+    writer.flush().unwrap();
+    while !writer.is_idle() {
+        let cqe = ring.take_completion(7).unwrap();
+        writer.complete(&cqe).unwrap();
+    }
+    assert_eq!(ring.sq_pending(), 0);
+    assert_eq!(ring.complete().map(|cqe| cqe.user_data), Some(9));
+    let mut buf = [0; 16];
+    let n = unsafe { nc::read(fds[0], &mut buf) }.unwrap() as usize;
+    assert_eq!(buf[..n], *b"abcdefghij");
+    for fd in fds {
+        unsafe { nc::close(fd) }.unwrap();
+    }
+}
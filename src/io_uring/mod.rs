@@ -1,26 +1,101 @@
 use core::{
+    cell::Cell,
     cmp::max,
     ffi::{c_uint, c_void},
-    ptr,
-    sync::atomic::{Ordering, fence},
+    mem, ptr,
+    sync::atomic::{AtomicU32, Ordering, fence},
 };
 
 use crate::io;
 
-type OpCode = nc::IOURING_OP;
+pub type OpCode = nc::IOURING_OP;
+
+const REGISTERED_BUFFERS: u8 = 1 << 0;
+const REGISTERED_FILES: u8 = 1 << 1;
+
+// not in `nc::IOURING_OP` yet
+pub const IORING_OP_READ_MULTISHOT: u8 = 49;
+pub const IORING_OP_FTRUNCATE: u8 = 55;
+const IORING_CQE_F_MORE: u32 = 1 << 1;
+pub const IORING_TIMEOUT_REALTIME: u32 = 1 << 3;
+pub const IORING_SQ_CQ_OVERFLOW: u32 = 1 << 1;
+const IORING_REGISTER_IOWQ_MAX_WORKERS: i32 = 19;
+
+pub trait Cqe {
+    fn result(&self) -> io::Result<u32>;
+    /// Id of the provided buffer the kernel picked for this completion.
+    fn buffer_id(&self) -> Option<u16>;
+    /// The request stays armed and will complete again.
+    fn has_more(&self) -> bool;
+}
+
+impl Cqe for nc::io_uring_cqe_t {
+    fn result(&self) -> io::Result<u32> {
+        match self.res {
+            res @ 0.. => Ok(res as _),
+            res => Err(-res),
+        }
+    }
+
+    fn buffer_id(&self) -> Option<u16> {
+        (self.flags & nc::IORING_CQE_F_BUFFER != 0)
+            .then_some((self.flags >> nc::IORING_CQE_BUFFER_SHIFT) as _)
+    }
+
+    fn has_more(&self) -> bool {
+        self.flags & IORING_CQE_F_MORE != 0
+    }
+}
 
 pub struct IoUring {
     params: nc::io_uring_params_t,
-    #[allow(unused)]
     fd: u32,
+    registered: Cell<u8>,
+    /// What [`IoUring::probe_supported_ops`] found the first time.
+    supported_ops: Cell<Option<[bool; 256]>>,
+    sq_head: Cell<u32>,
     queue: *mut c_void,
     sqes: *mut nc::io_uring_sqe_t,
 }
 
+#[derive(Default)]
+pub struct Builder {
+    sqpoll: Option<u32>,
+}
+
+impl Builder {
+    /// Let a kernel thread poll the submission queue, going to sleep after `idle_ms`
+    /// without work.
+    pub const fn sqpoll(mut self, idle_ms: u32) -> Self {
+        self.sqpoll = Some(idle_ms);
+        self
+    }
+
+    /// Falls back to a plain ring when the kernel doesn't allow SQPOLL to this user.
+    pub fn build(self, size: u32) -> io::Result<IoUring> {
+        let mut params = nc::io_uring_params_t::default();
+        if let Some(idle_ms) = self.sqpoll {
+            params.flags |= nc::IORING_SETUP_SQPOLL;
+            params.sq_thread_idle = idle_ms;
+        }
+        match IoUring::setup(size, params) {
+            Err(nc::EPERM) if self.sqpoll.is_some() => Builder::default().build(size),
+            x => x,
+        }
+    }
+}
+
 impl IoUring {
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
     #[inline]
     pub fn new(size: u32) -> io::Result<Self> {
-        let mut params = nc::io_uring_params_t::default();
+        Self::builder().build(size)
+    }
+
+    fn setup(size: u32, mut params: nc::io_uring_params_t) -> io::Result<Self> {
         let fd = unsafe { nc::io_uring_setup(size, &mut params)? };
 
         let queue_size = max(
@@ -51,11 +126,108 @@ impl IoUring {
         Ok(Self {
             params,
             fd,
+            registered: Cell::new(0),
+            supported_ops: Cell::new(None),
+            sq_head: Cell::new(0),
             queue,
             sqes,
         })
     }
 
+    /// Next slot to be filled.
+    pub fn peek_sq_tail(&self) -> u32 {
+        let tail = unsafe { self.queue.add(self.params.sq_off.tail as usize) } as *const AtomicU32;
+        unsafe { (*tail).load(Ordering::Acquire) }
+    }
+
+    /// Last slot the kernel was seen to consume; may lag behind the real head.
+    pub fn sq_head_cached(&self) -> u32 {
+        self.sq_head.get()
+    }
+
+    /// Entries queued but not yet taken by the kernel, which takes them oldest first whatever
+    /// asked for the submit.
+    pub fn sq_pending(&self) -> u32 {
+        let head = unsafe { self.queue.add(self.params.sq_off.head as usize) } as *const AtomicU32;
+        self.peek_sq_tail()
+            .wrapping_sub(unsafe { (*head).load(Ordering::Acquire) })
+    }
+
+    pub fn sq_space_left(&self) -> u32 {
+        let (entries, tail) = (self.params.sq_entries, self.peek_sq_tail());
+        let used = tail.wrapping_sub(self.sq_head_cached());
+        if used < entries {
+            return entries - used;
+        }
+        let head = unsafe { self.queue.add(self.params.sq_off.head as usize) } as *const AtomicU32;
+        self.sq_head.set(unsafe { (*head).load(Ordering::Acquire) });
+        entries - tail.wrapping_sub(self.sq_head_cached())
+    }
+
+    /// Fills in the next entry, submitting the ones queued so far first if there is no room.
+    fn push(&self, fill: impl FnOnce(&mut nc::io_uring_sqe_t)) -> io::Result<()> {
+        if self.sq_space_left() == 0 {
+            self.submit(self.params.sq_entries)?;
+            if self.sq_space_left() == 0 {
+                return Err(nc::EBUSY);
+            }
+        }
+        let tail = unsafe { self.queue.add(self.params.sq_off.tail as usize) } as *mut u32;
+        let mask = unsafe { self.queue.add(self.params.sq_off.ring_mask as usize) } as *mut u32;
+        let array = unsafe { self.queue.add(self.params.sq_off.array as usize) } as *mut u32;
+
+        let index = unsafe { *tail & *mask };
+        let sqe = unsafe { self.sqes.add(index as usize) };
+        // not `Default`, which leaves the tail of the `opt_buf` union undefined
+        unsafe { ptr::write_bytes(sqe, 0, 1) };
+        fill(unsafe { &mut *sqe });
+
+        unsafe { *array.add(index as usize) = index };
+        // an SQPOLL thread may pick the entry up as soon as the tail moves
+        unsafe { (*(tail as *const AtomicU32)).fetch_add(1, Ordering::Release) };
+        Ok(())
+    }
+
+    fn sqpoll(&self) -> bool {
+        self.params.flags & nc::IORING_SETUP_SQPOLL != 0
+    }
+
+    /// Always true without SQPOLL; with it, only once the kernel thread went idle and
+    /// has to be woken up.
+    pub fn sq_ring_needs_enter(&self) -> bool {
+        if !self.sqpoll() {
+            return true;
+        }
+        // order the tail store before reading the flags the kernel thread sets
+        fence(Ordering::SeqCst);
+        self.sq_flags() & nc::IORING_SQ_NEED_WAKEUP != 0
+    }
+
+    fn sq_flags(&self) -> u32 {
+        let flags =
+            unsafe { self.queue.add(self.params.sq_off.flags as usize) } as *const AtomicU32;
+        unsafe { (*flags).load(Ordering::Relaxed) }
+    }
+
+    /// Completions that didn't fit the CQ ring are held back by the kernel until flushed.
+    pub fn cq_overflow(&self) -> bool {
+        self.sq_flags() & IORING_SQ_CQ_OVERFLOW != 0
+    }
+
+    /// Moves held-back completions into whatever room the CQ ring has.
+    pub fn flush_overflow(&self) -> io::Result<()> {
+        self.enter(0, 0, nc::IORING_ENTER_GETEVENTS, ptr::null())
+            .map(drop)
+    }
+
+    fn wakeup_flag(&self) -> u32 {
+        match self.sqpoll() && self.sq_ring_needs_enter() {
+            true => nc::IORING_ENTER_SQ_WAKEUP,
+            false => 0,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn prepare(
         &self,
         op_code: OpCode,
@@ -64,38 +236,98 @@ impl IoUring {
         len: usize,
         user_data: usize,
         timeout_flags: u32,
-    ) {
-        let tail = unsafe { self.queue.add(self.params.sq_off.tail as usize) } as *mut u32;
-        let mask = unsafe { self.queue.add(self.params.sq_off.ring_mask as usize) } as *mut u32;
-        let array = unsafe { self.queue.add(self.params.sq_off.array as usize) } as *mut u32;
+        sqe_flags: u8,
+    ) -> io::Result<()> {
+        self.push(|sqe| {
+            sqe.opcode = op_code as _;
+            sqe.flags = sqe_flags;
+            sqe.fd = fd as i32;
+            sqe.buf_addr.addr = addr as _;
+            sqe.len = len as u32;
+            sqe.user_data = user_data as u64;
+            sqe.other_flags.timeout_flags = timeout_flags;
+        })
+    }
 
-        let index = unsafe { *tail & *mask };
-        let sqe = unsafe { &mut *self.sqes.add(index as usize) };
-        sqe.opcode = op_code as _;
-        sqe.fd = fd as i32;
-        sqe.buf_addr.addr = addr as _;
-        sqe.len = len as u32;
-        sqe.user_data = user_data as u64;
-        sqe.other_flags.timeout_flags = timeout_flags;
+    pub fn peek(&self) -> Option<&nc::io_uring_cqe_t> {
+        let head = unsafe { self.queue.add(self.params.cq_off.head as usize) } as *const u32;
+        let tail = unsafe { self.queue.add(self.params.cq_off.tail as usize) } as *const AtomicU32;
+        let mask = unsafe { self.queue.add(self.params.cq_off.ring_mask as usize) } as *const u32;
+        let cqes =
+            unsafe { self.queue.add(self.params.cq_off.cqes as usize) } as *mut nc::io_uring_cqe_t;
 
-        unsafe { *array.add(index as usize) = index };
-        fence(Ordering::SeqCst);
-        unsafe { *tail += 1 };
+        let head = unsafe { *head };
+        if head == unsafe { (*tail).load(Ordering::Acquire) } {
+            return None;
+        }
+        Some(unsafe { &*cqes.add((head & *mask) as usize) })
+    }
+
+    fn advance(&self) {
+        let head = unsafe { self.queue.add(self.params.cq_off.head as usize) } as *const AtomicU32;
+        unsafe { (*head).fetch_add(1, Ordering::Release) };
     }
 
-    pub fn complete(&self) -> &nc::io_uring_cqe_t {
-        let head = unsafe { self.queue.add(self.params.cq_off.head as usize) } as *mut u32;
-        let mask = unsafe { self.queue.add(self.params.cq_off.ring_mask as usize) } as *mut u32;
+    pub fn complete(&self) -> Option<nc::io_uring_cqe_t> {
+        let cqe = *self.peek()?;
+        self.advance();
+        Some(cqe)
+    }
+
+    /// Waits for a completion of `user_data` and takes it out of the queue ahead of the ones before
+    /// it, which stay where they were for [`IoUring::for_each_completion`].
+    pub fn take_completion(&self, user_data: usize) -> io::Result<nc::io_uring_cqe_t> {
+        let head = unsafe { self.queue.add(self.params.cq_off.head as usize) } as *const u32;
+        let tail = unsafe { self.queue.add(self.params.cq_off.tail as usize) } as *const AtomicU32;
+        let mask =
+            unsafe { *(self.queue.add(self.params.cq_off.ring_mask as usize) as *const u32) };
         let cqes =
             unsafe { self.queue.add(self.params.cq_off.cqes as usize) } as *mut nc::io_uring_cqe_t;
+        loop {
+            let (head, tail) = (unsafe { *head }, unsafe { (*tail).load(Ordering::Acquire) });
+            let slot = |i: u32| unsafe { cqes.add((i & mask) as usize) };
+            let mut i = head;
+            while i != tail && unsafe { (*slot(i)).user_data } != user_data as u64 {
+                i = i.wrapping_add(1);
+            }
+            if i == tail {
+                // what it waits for may still be sitting in the SQ
+                let flags = nc::IORING_ENTER_GETEVENTS | self.wakeup_flag();
+                let to_submit = self.sq_pending();
+                self.enter(to_submit, tail.wrapping_sub(head) + 1, flags, ptr::null())?;
+                continue;
+            }
+            let cqe = unsafe { *slot(i) };
+            // the ones in front move up a slot into its place
+            while i != head {
+                unsafe { *slot(i) = *slot(i.wrapping_sub(1)) };
+                i = i.wrapping_sub(1);
+            }
+            self.advance();
+            return Ok(cqe);
+        }
+    }
 
-        let cqe = unsafe { &*cqes.add((*head & *mask) as usize) };
-        fence(Ordering::SeqCst);
-        unsafe { *head += 1 };
-        cqe
+    pub fn overflow(&self) -> u32 {
+        let overflow =
+            unsafe { self.queue.add(self.params.cq_off.overflow as usize) } as *const AtomicU32;
+        unsafe { (*overflow).load(Ordering::Acquire) }
     }
 
-    pub fn prepare_read(&self, fd: usize, buf: &mut [u8], user_data: usize) {
+    pub fn for_each_completion(&self, mut f: impl FnMut(&nc::io_uring_cqe_t)) -> io::Result<u32> {
+        if self.overflow() != 0 {
+            return Err(nc::EOVERFLOW);
+        }
+        let mut n = 0;
+        while let Some(cqe) = self.peek() {
+            f(cqe);
+            self.advance();
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    pub fn prepare_read(&self, fd: usize, buf: &mut [u8], user_data: usize) -> io::Result<()> {
         self.prepare(
             OpCode::IORING_OP_READ,
             fd,
@@ -103,10 +335,97 @@ impl IoUring {
             buf.len(),
             user_data,
             0,
+            0,
         )
     }
 
-    pub fn prepare_timeout(&self, duration: &nc::timespec_t, user_data: usize, flags: u32) {
+    /// `file_index` and `buf_index` refer to [`IoUring::register_files`] and
+    /// [`IoUring::register_buffers`]; `buf` must lie inside the registered buffer.
+    pub fn prepare_read_fixed(
+        &self,
+        file_index: u32,
+        buf: &mut [u8],
+        buf_index: u16,
+        user_data: usize,
+        sqe_flags: u8,
+    ) -> io::Result<()> {
+        self.push(|sqe| {
+            sqe.opcode = OpCode::IORING_OP_READ_FIXED;
+            sqe.flags = nc::IOSQE_FIXED_FILE as u8 | sqe_flags;
+            sqe.fd = file_index as _;
+            sqe.buf_addr.addr = buf.as_ptr() as _;
+            sqe.len = buf.len() as _;
+            sqe.user_data = user_data as _;
+            sqe.opt_buf.buf.group.buf_index = buf_index;
+        })
+    }
+
+    /// Hands `bufs` to the kernel as group `group`, with ids counting up from `first_id`.
+    pub fn prepare_provide_buffers<const N: usize>(
+        &self,
+        bufs: &mut [[u8; N]],
+        group: u16,
+        first_id: u16,
+        user_data: usize,
+    ) -> io::Result<()> {
+        self.push(|sqe| {
+            sqe.opcode = OpCode::IORING_OP_PROVIDE_BUFFERS;
+            sqe.fd = bufs.len() as _;
+            sqe.buf_addr.addr = bufs.as_ptr() as _;
+            sqe.len = N as _;
+            sqe.file_off.off = first_id as _;
+            sqe.user_data = user_data as _;
+            sqe.opt_buf.buf.group.buf_group = group;
+        })
+    }
+
+    /// Keeps reading `fd` into buffers taken from `group` until it fails, runs out of
+    /// buffers or hits EOF; see [`Cqe::buffer_id`] and [`Cqe::has_more`].
+    pub fn prepare_read_multishot(
+        &self,
+        fd: usize,
+        group: u16,
+        user_data: usize,
+        sqe_flags: u8,
+    ) -> io::Result<()> {
+        self.push(|sqe| {
+            unsafe { *(&raw mut sqe.opcode as *mut u8) = IORING_OP_READ_MULTISHOT };
+            sqe.flags = nc::IOSQE_BUFFER_SELECT as u8 | sqe_flags;
+            sqe.fd = fd as _;
+            sqe.user_data = user_data as _;
+            sqe.opt_buf.buf.group.buf_group = group;
+        })
+    }
+
+    pub fn prepare_write(&self, fd: usize, buf: &[u8], user_data: usize) -> io::Result<()> {
+        self.prepare(
+            OpCode::IORING_OP_WRITE,
+            fd,
+            buf.as_ptr() as usize,
+            buf.len(),
+            user_data,
+            0,
+            0,
+        )
+    }
+
+    /// Flushes `fd` to disk, only its data with `nc::IORING_FSYNC_DATASYNC` in `flags`.
+    /// `fd` has to be a regular file; sockets and pipes fail with `EINVAL`.
+    pub fn prepare_fsync(&self, fd: usize, flags: u32, user_data: usize) -> io::Result<()> {
+        self.push(|sqe| {
+            sqe.opcode = OpCode::IORING_OP_FSYNC;
+            sqe.fd = fd as _;
+            sqe.user_data = user_data as _;
+            sqe.other_flags.fsync_flags = flags;
+        })
+    }
+
+    pub fn prepare_timeout(
+        &self,
+        duration: &nc::timespec_t,
+        user_data: usize,
+        flags: u32,
+    ) -> io::Result<()> {
         self.prepare(
             OpCode::IORING_OP_TIMEOUT,
             usize::MAX,
@@ -114,7 +433,143 @@ impl IoUring {
             1,
             user_data,
             flags,
-        );
+            0,
+        )
+    }
+
+    /// A timeout the SQE prepared right after waits for, as if it were a write still in
+    /// flight; unlike a plain link its `ETIME` doesn't cancel the rest of the chain.
+    pub fn prepare_hardlinked_timeout(
+        &self,
+        duration: &nc::timespec_t,
+        user_data: usize,
+    ) -> io::Result<()> {
+        self.prepare(
+            OpCode::IORING_OP_TIMEOUT,
+            usize::MAX,
+            duration as *const _ as usize,
+            1,
+            user_data,
+            0,
+            nc::IOSQE_IO_HARDLINK as _,
+        )
+    }
+
+    /// Cuts `fd` down to `length` bytes, so a log can start over once it grows too big.
+    /// Behind [`IoUring::prepare_hardlinked_timeout`] it waits for the writes to quiet down.
+    /// Needs a 6.9 kernel, see [`IoUring::has_op_ftruncate`].
+    pub fn prepare_ftruncate(&self, fd: i32, length: i64, user_data: usize) -> io::Result<()> {
+        self.push(|sqe| {
+            unsafe { *(&raw mut sqe.opcode as *mut u8) = IORING_OP_FTRUNCATE };
+            sqe.fd = fd;
+            sqe.file_off.off = length as _;
+            sqe.user_data = user_data as _;
+        })
+    }
+
+    pub fn has_op_ftruncate(&self) -> bool {
+        self.probe(IORING_OP_FTRUNCATE)
+    }
+
+    /// Cancels the pending timeout submitted with `target` as its user data.
+    pub fn prepare_timeout_remove(&self, target: usize, user_data: usize) -> io::Result<()> {
+        self.prepare(
+            OpCode::IORING_OP_TIMEOUT_REMOVE,
+            usize::MAX,
+            target,
+            0,
+            user_data,
+            0,
+            0,
+        )
+    }
+
+    /// Cancels the SQE prepared right before, which must carry `IOSQE_IO_LINK`, if it
+    /// hasn't completed within `duration`.
+    pub fn prepare_linked_timeout(
+        &self,
+        duration: &nc::timespec_t,
+        user_data: usize,
+    ) -> io::Result<()> {
+        self.prepare(
+            OpCode::IORING_OP_LINK_TIMEOUT,
+            usize::MAX,
+            duration as *const _ as usize,
+            1,
+            user_data,
+            0,
+            0,
+        )
+    }
+
+    fn register(&self, opcode: i32, arg: usize, nr_args: usize) -> io::Result<()> {
+        unsafe { nc::io_uring_register(self.fd, opcode as _, arg, nr_args as _) }?;
+        Ok(())
+    }
+
+    /// Whether the running kernel knows `op`. Kernels without `IORING_REGISTER_PROBE`
+    /// report nothing as supported.
+    pub fn probe(&self, op: u8) -> bool {
+        self.probe_supported_ops().is_ok_and(|ops| ops[op as usize])
+    }
+
+    /// Every opcode the running kernel knows, asked once with `IORING_REGISTER_PROBE`.
+    pub fn probe_supported_ops(&self) -> io::Result<[bool; 256]> {
+        #[repr(C)]
+        struct Probe {
+            header: nc::io_uring_probe_t,
+            ops: [nc::io_uring_probe_op_t; 256],
+        }
+        if let Some(ops) = self.supported_ops.get() {
+            return Ok(ops);
+        }
+        let mut probe: Probe = unsafe { mem::zeroed() };
+        self.register(nc::IORING_REGISTER_PROBE, &raw mut probe as _, 256)?;
+        let mut ops = [false; 256];
+        for (supported, op) in ops.iter_mut().zip(&probe.ops[..probe.header.ops_len as _]) {
+            *supported = op.flags as u32 & nc::IO_URING_OP_SUPPORTED != 0;
+        }
+        self.supported_ops.set(Some(ops));
+        Ok(ops)
+    }
+
+    /// Caps the io-wq threads serving blocking requests at `bounded` and `unbounded`, per NUMA
+    /// node. Zero leaves that count as it is.
+    pub fn register_iowq_max_workers(&self, bounded: u32, unbounded: u32) -> io::Result<()> {
+        let mut counts = [bounded, unbounded];
+        self.register(
+            IORING_REGISTER_IOWQ_MAX_WORKERS,
+            counts.as_mut_ptr() as _,
+            counts.len(),
+        )
+    }
+
+    pub fn register_buffers(&self, iovecs: &[nc::iovec_t]) -> io::Result<()> {
+        self.register(
+            nc::IORING_REGISTER_BUFFERS,
+            iovecs.as_ptr() as _,
+            iovecs.len(),
+        )?;
+        self.registered
+            .set(self.registered.get() | REGISTERED_BUFFERS);
+        Ok(())
+    }
+
+    pub fn register_files(&self, fds: &[i32]) -> io::Result<()> {
+        self.register(nc::IORING_REGISTER_FILES, fds.as_ptr() as _, fds.len())?;
+        self.registered
+            .set(self.registered.get() | REGISTERED_FILES);
+        Ok(())
+    }
+
+    /// Drops what [`IoUring::register_files`] registered, if anything.
+    pub fn unregister_files(&self) -> io::Result<()> {
+        if self.registered.get() & REGISTERED_FILES != 0 {
+            self.register(nc::IORING_UNREGISTER_FILES, 0, 0)?;
+            self.registered
+                .set(self.registered.get() & !REGISTERED_FILES);
+        }
+        Ok(())
     }
 
     pub fn enter(
@@ -128,7 +583,8 @@ impl IoUring {
     }
 
     fn submit_wait_mask_impl(&self, to_submit: u32, sigset: *const c_void) -> io::Result<i32> {
-        self.enter(to_submit, 1, nc::IORING_ENTER_GETEVENTS, sigset)
+        let flags = nc::IORING_ENTER_GETEVENTS | self.wakeup_flag();
+        self.enter(to_submit, 1, flags, sigset)
     }
 
     pub fn submit_wait_mask(&self, to_submit: u32, sigset: &nc::sigset_t) -> io::Result<i32> {
@@ -136,7 +592,18 @@ impl IoUring {
     }
 
     pub fn submit(&self, to_submit: u32) -> io::Result<i32> {
-        self.enter(to_submit, 0, 0, ptr::null())
+        match (self.sqpoll(), self.sq_ring_needs_enter()) {
+            (true, false) => Ok(to_submit as _),
+            (true, true) => self.enter(to_submit, 0, nc::IORING_ENTER_SQ_WAKEUP, ptr::null()),
+            (false, _) => self.enter(to_submit, 0, 0, ptr::null()),
+        }
+    }
+
+    /// Submits whatever `prepare` queued, along with anything queued before it: the kernel
+    /// starts from the oldest entry, so counting only this batch could leave part of it behind.
+    pub fn submit_batch(&self, prepare: impl FnOnce(&Self) -> io::Result<()>) -> io::Result<i32> {
+        prepare(self)?;
+        self.submit(self.sq_pending())
     }
 
     pub fn submit_wait(&self, to_submit: u32) -> io::Result<i32> {
@@ -147,3 +614,356 @@ impl IoUring {
         self.submit_wait_mask_impl(0, ptr::null())
     }
 }
+
+impl Drop for IoUring {
+    fn drop(&mut self) {
+        if self.registered.get() & REGISTERED_BUFFERS != 0 {
+            _ = self.register(nc::IORING_UNREGISTER_BUFFERS, 0, 0);
+        }
+        _ = self.unregister_files();
+        _ = unsafe { nc::close(self.fd as _) };
+    }
+}
+
+/// A read into `buf` that keeps it borrowed while the read may be in flight, so the buffer can't
+/// be reused or go away before the completion is looked at.
+pub struct IoUringReadHandle<'a> {
+    ring: &'a IoUring,
+    fd: usize,
+    buf: &'a mut [u8],
+    user_data: usize,
+}
+
+impl<'a> IoUringReadHandle<'a> {
+    pub fn new(ring: &'a IoUring, fd: usize, buf: &'a mut [u8], user_data: usize) -> Self {
+        Self {
+            ring,
+            fd,
+            buf,
+            user_data,
+        }
+    }
+
+    /// Queues a read of `fd` into the whole buffer.
+    pub fn arm(&mut self, sqe_flags: u8) -> io::Result<()> {
+        let (addr, len) = (self.buf.as_mut_ptr() as usize, self.buf.len());
+        let op = OpCode::IORING_OP_READ;
+        self.ring
+            .prepare(op, self.fd, addr, len, self.user_data, 0, sqe_flags)
+    }
+
+    /// [`IoUringReadHandle::arm`] through registered file `file_index` and registered buffer
+    /// `buf_index`, which the buffer must lie inside of.
+    pub fn arm_fixed(&mut self, file_index: u32, buf_index: u16, sqe_flags: u8) -> io::Result<()> {
+        self.ring
+            .prepare_read_fixed(file_index, self.buf, buf_index, self.user_data, sqe_flags)
+    }
+
+    /// The bytes `cqe` read, or EINVAL if it completes some other request.
+    pub fn result(&self, cqe: &nc::io_uring_cqe_t) -> io::Result<&[u8]> {
+        if cqe.user_data != self.user_data as u64 || cqe.buffer_id().is_some() {
+            return Err(nc::EINVAL);
+        }
+        self.buf.get(..cqe.result()? as usize).ok_or(nc::EOVERFLOW)
+    }
+}
+
+#[test]
+fn test_read_fixed() {
+    let ring = IoUring::new(4).unwrap();
+    let mut fds = [0; 2];
+    unsafe { nc::pipe2(&mut fds, nc::O_CLOEXEC) }.unwrap();
+    let mut buf = [0u8; 16];
+    ring.register_files(&fds[..1]).unwrap();
+    ring.register_buffers(&[nc::iovec_t {
+        iov_base: buf.as_ptr() as _,
+        iov_len: buf.len(),
+    }])
+    .unwrap();
+    unsafe { nc::write(fds[1], b"abc") }.unwrap();
+    ring.prepare_read_fixed(0, &mut buf, 0, 7, 0).unwrap();
+    ring.submit_wait(1).unwrap();
+    let cqe = ring.complete().unwrap();
+    assert_eq!((cqe.user_data, cqe.res), (7, 3));
+    assert_eq!(buf[..3], *b"abc");
+    ring.unregister_files().unwrap();
+    ring.unregister_files().unwrap();
+    for fd in fds {
+        unsafe { nc::close(fd) }.unwrap();
+    }
+}
+
+#[test]
+fn test_read_multishot() {
+    let ring = IoUring::new(4).unwrap();
+    if !ring.probe(IORING_OP_READ_MULTISHOT) {
+        return;
+    }
+    let mut fds = [0; 2];
+    unsafe { nc::pipe2(&mut fds, nc::O_CLOEXEC) }.unwrap();
+    let mut bufs = [[0u8; 4]; 2];
+    ring.prepare_provide_buffers(&mut bufs, 1, 0, 1).unwrap();
+    ring.prepare_read_multishot(fds[0] as _, 1, 2, 0).unwrap();
+    ring.submit_wait(2).unwrap();
+    assert_eq!(ring.complete().unwrap().result(), Ok(0));
+
+    for (i, data) in [b"ab", b"cd"].iter().enumerate() {
+        unsafe { nc::write(fds[1], *data) }.unwrap();
+        ring.wait().unwrap();
+        let cqe = ring.complete().unwrap();
+        assert_eq!((cqe.user_data, cqe.result()), (2, Ok(2)));
+        assert!(cqe.has_more());
+        let id = cqe.buffer_id().unwrap() as usize;
+        assert_eq!(id, i);
+        assert_eq!(bufs[id][..2], data[..]);
+    }
+    for fd in fds {
+        unsafe { nc::close(fd) }.unwrap();
+    }
+}
+
+#[test]
+fn test_ftruncate() {
+    let ring = IoUring::new(4).unwrap();
+    if !ring.has_op_ftruncate() {
+        return;
+    }
+    let name = c"test_ftruncate";
+    let flags = nc::MFD_CLOEXEC as usize;
+    let fd = unsafe { nc::syscalls::syscall2(nc::SYS_MEMFD_CREATE, name.as_ptr() as _, flags) };
+    let fd = fd.unwrap() as i32;
+    unsafe { nc::write(fd, b"0123456789") }.unwrap();
+    let quiet = nc::timespec_t {
+        tv_sec: 0,
+        tv_nsec: 1_000_000,
+    };
+    ring.prepare_hardlinked_timeout(&quiet, 1).unwrap();
+    ring.prepare_ftruncate(fd, 4, 2).unwrap();
+    ring.submit(2).unwrap();
+    let mut results = [None; 2];
+    while results.contains(&None) {
+        ring.wait().unwrap();
+        while let Some(cqe) = ring.complete() {
+            results[cqe.user_data as usize - 1] = Some(cqe.result());
+        }
+    }
+    assert_eq!(results, [Some(Err(nc::ETIME)), Some(Ok(0))]);
+    let mut stat = nc::stat_t::default();
+    unsafe { nc::fstat(fd, &mut stat) }.unwrap();
+    assert_eq!(stat.st_size, 4);
+    unsafe { nc::close(fd) }.unwrap();
+}
+
+#[test]
+fn test_fsync() {
+    let ring = IoUring::new(4).unwrap();
+    let name = c"test_fsync";
+    let flags = nc::MFD_CLOEXEC as usize;
+    let fd = unsafe { nc::syscalls::syscall2(nc::SYS_MEMFD_CREATE, name.as_ptr() as _, flags) };
+    let fd = fd.unwrap();
+    unsafe { nc::write(fd as _, b"0123456789") }.unwrap();
+    ring.prepare_fsync(fd, nc::IORING_FSYNC_DATASYNC, 1)
+        .unwrap();
+    let sqe = unsafe { &*ring.sqes };
+    assert_eq!(sqe.opcode as u8, OpCode::IORING_OP_FSYNC as u8);
+    assert_eq!(
+        unsafe { sqe.other_flags.fsync_flags },
+        nc::IORING_FSYNC_DATASYNC
+    );
+    assert_eq!(sqe.fd, fd as i32);
+    ring.submit(1).unwrap();
+    ring.wait().unwrap();
+    assert_eq!(ring.complete().map(|cqe| cqe.result()), Some(Ok(0)));
+    unsafe { nc::close(fd as _) }.unwrap();
+}
+
+#[test]
+fn test_sq_space_left() {
+    let ring = IoUring::new(2).unwrap();
+    let duration = nc::timespec_t::default();
+    assert_eq!(ring.sq_space_left(), 2);
+    ring.prepare_timeout(&duration, 1, 0).unwrap();
+    ring.prepare_timeout(&duration, 2, 0).unwrap();
+    assert_eq!((ring.peek_sq_tail(), ring.sq_space_left()), (2, 0));
+    ring.submit(2).unwrap();
+    assert_eq!(ring.sq_head_cached(), 0);
+    assert_eq!(ring.sq_space_left(), 2);
+    assert_eq!(ring.sq_head_cached(), 2);
+    // a full queue is submitted to make room
+    for user_data in 3..=5 {
+        ring.prepare_timeout(&duration, user_data, 0).unwrap();
+    }
+    assert_eq!((ring.sq_head_cached(), ring.peek_sq_tail()), (4, 5));
+}
+
+#[test]
+fn test_submit_batch() {
+    let ring = IoUring::new(4).unwrap();
+    let duration = nc::timespec_t::default();
+    ring.prepare_timeout(&duration, 1, 0).unwrap();
+    ring.submit(1).unwrap();
+    let tail = ring.peek_sq_tail();
+    let submitted = ring.submit_batch(|ring| {
+        for user_data in 2..=4 {
+            ring.prepare_timeout(&duration, user_data, 0).unwrap();
+        }
+        Ok(())
+    });
+    assert_eq!(submitted, Ok(3));
+    assert_eq!(ring.peek_sq_tail().wrapping_sub(tail), 3);
+    assert_eq!(ring.submit_batch(|_| Ok(())), Ok(0));
+    assert_eq!(ring.submit_batch(|_| Err(nc::EINVAL)), Err(nc::EINVAL));
+}
+
+#[test]
+fn test_cq_overflow() {
+    let ring = IoUring::new(1).unwrap();
+    let cq_entries = ring.params.cq_entries;
+    for user_data in 0..=cq_entries {
+        ring.prepare(OpCode::IORING_OP_NOP, 0, 0, 0, user_data as _, 0, 0)
+            .unwrap();
+        ring.submit(1).unwrap();
+    }
+    assert!(ring.cq_overflow());
+    let mut seen = 0;
+    while let Some(cqe) = ring.complete() {
+        assert_eq!(cqe.user_data, seen);
+        seen += 1;
+    }
+    assert_eq!(seen, cq_entries as _);
+    ring.flush_overflow().unwrap();
+    assert!(!ring.cq_overflow());
+    assert_eq!(ring.complete().map(|cqe| cqe.user_data), Some(seen));
+}
+
+#[test]
+fn test_sqpoll() {
+    let ring = IoUring::builder().sqpoll(10).build(4).unwrap();
+    let duration = nc::timespec_t::default();
+    for user_data in 1..=3 {
+        ring.prepare_timeout(&duration, user_data, 0).unwrap();
+        if user_data > 1 {
+            assert!(ring.sq_ring_needs_enter());
+        }
+        ring.submit(1).unwrap();
+        ring.wait().unwrap();
+        let cqe = ring.complete().unwrap();
+        assert_eq!(
+            (cqe.user_data, cqe.result()),
+            (user_data as _, Err(nc::ETIME))
+        );
+        // let the poller go idle so the next submit has to wake it
+        unsafe {
+            nc::nanosleep(
+                &nc::timespec_t {
+                    tv_sec: 0,
+                    tv_nsec: 20_000_000,
+                },
+                None,
+            )
+        }
+        .unwrap();
+    }
+}
+
+#[test]
+fn test_linked_timeout() {
+    let ring = IoUring::new(4).unwrap();
+    let mut fds = [0; 2];
+    unsafe { nc::pipe2(&mut fds, nc::O_CLOEXEC) }.unwrap();
+    let mut buf = [0; 4];
+    let duration = nc::timespec_t {
+        tv_sec: 0,
+        tv_nsec: 10_000_000,
+    };
+    ring.prepare(
+        OpCode::IORING_OP_READ,
+        fds[0] as _,
+        buf.as_mut_ptr() as _,
+        buf.len(),
+        1,
+        0,
+        nc::IOSQE_IO_LINK as _,
+    )
+    .unwrap();
+    ring.prepare_linked_timeout(&duration, 2).unwrap();
+    ring.submit(2).unwrap();
+    let mut results = [None; 2];
+    while results.contains(&None) {
+        ring.wait().unwrap();
+        while let Some(cqe) = ring.complete() {
+            results[cqe.user_data as usize - 1] = Some(cqe.result());
+        }
+    }
+    assert_eq!(results, [Some(Err(nc::ECANCELED)), Some(Err(nc::ETIME))]);
+    for fd in fds {
+        unsafe { nc::close(fd) }.unwrap();
+    }
+}
+
+#[test]
+fn test_timeout_remove() {
+    let ring = IoUring::new(4).unwrap();
+    let duration = nc::timespec_t {
+        tv_sec: 10,
+        tv_nsec: 0,
+    };
+    ring.prepare_timeout(&duration, 1, 1 << 6).unwrap();
+    ring.prepare_timeout_remove(1, 2).unwrap();
+    ring.submit(2).unwrap();
+    let mut results = [None; 2];
+    while results.contains(&None) {
+        ring.wait().unwrap();
+        while let Some(cqe) = ring.complete() {
+            results[cqe.user_data as usize - 1] = Some(cqe.result());
+        }
+    }
+    assert_eq!(results, [Some(Err(nc::ECANCELED)), Some(Ok(0))]);
+}
+
+#[test]
+fn test_register_iowq_max_workers() {
+    let ring = IoUring::new(4).unwrap();
+    ring.register_iowq_max_workers(1, 0).unwrap();
+}
+
+#[test]
+fn test_probe_supported_ops() {
+    let ring = IoUring::new(4).unwrap();
+    let ops = ring.probe_supported_ops().unwrap();
+    assert!(ops[OpCode::IORING_OP_READ as usize] && ops[OpCode::IORING_OP_TIMEOUT as usize]);
+    assert!(!ops[255]);
+    assert_eq!(ring.probe_supported_ops(), Ok(ops));
+    assert_eq!(
+        ring.probe(IORING_OP_READ_MULTISHOT),
+        ops[IORING_OP_READ_MULTISHOT as usize]
+    );
+}
+
+#[test]
+fn test_read_handle() {
+    let ring = IoUring::new(4).unwrap();
+    let mut fds = [0; 2];
+    unsafe { nc::pipe2(&mut fds, nc::O_CLOEXEC) }.unwrap();
+    let mut buf = [0u8; 16];
+    let addr = buf.as_ptr() as u64;
+    let mut read = IoUringReadHandle::new(&ring, fds[0] as _, &mut buf, 3);
+    read.arm(0).unwrap();
+    let sqe = unsafe { &*ring.sqes };
+    assert_eq!(sqe.opcode as u8, OpCode::IORING_OP_READ as u8);
+    assert_eq!((unsafe { sqe.buf_addr.addr }, sqe.len), (addr, 16));
+    unsafe { nc::write(fds[1], b"abc") }.unwrap();
+    ring.submit_wait(1).unwrap();
+    let cqe = ring.complete().unwrap();
+    assert_eq!(read.result(&cqe), Ok(&b"abc"[..]));
+    let other = nc::io_uring_cqe_t {
+        user_data: 4,
+        ..cqe
+    };
+    assert_eq!(read.result(&other), Err(nc::EINVAL));
+    let long = nc::io_uring_cqe_t { res: 17, ..cqe };
+    assert_eq!(read.result(&long), Err(nc::EOVERFLOW));
+    for fd in fds {
+        unsafe { nc::close(fd) }.unwrap();
+    }
+}
@@ -4,14 +4,27 @@
 #![feature(concat_bytes, const_trait_impl)]
 
 use core::{
-    alloc::GlobalAlloc, arch::naked_asm, cell::Cell, mem::MaybeUninit, panic::PanicInfo,
+    alloc::GlobalAlloc,
+    arch::naked_asm,
+    cell::Cell,
+    mem::{self, MaybeUninit},
+    panic::PanicInfo,
     ptr::null_mut,
+    sync::atomic::{AtomicBool, Ordering},
 };
 
-use draw::draw_time;
-use io::{ArrayWriter, BufWriter, FdWriter, Write as _};
-use io_uring::IoUring;
+use args::ArgIter;
+use draw::{
+    ClockFormat, ClockMode, Config, Style,
+    color::{self, Ink},
+    draw_time, draw_time_braun_style,
+};
+use io::{ArrayWriter, BufWriter, FdWriter, UringWriter, Write as _};
+use io_uring::{
+    Cqe as _, IORING_OP_FTRUNCATE, IORING_OP_READ_MULTISHOT, IoUring, IoUringReadHandle,
+};
 
+pub mod args;
 pub mod draw;
 pub mod io;
 pub mod io_uring;
@@ -20,14 +33,14 @@ pub mod io_uring;
 #[macro_export]
 macro_rules! print {
     ($($arg:tt)*) => {
-        core::fmt::Write::write_fmt(&mut crate::io::FdWriter::stdout(), format_args!($($arg)*)).unwrap()
+        crate::io::WriteFmt::write_fmt(&mut crate::io::FdWriter::stdout(), format_args!($($arg)*)).unwrap()
     }
 }
 
 #[macro_export]
 macro_rules! eprint {
     ($($arg:tt)*) => {
-        core::fmt::Write::write_fmt(&mut crate::io::FdWriter::stderr(), format_args!($($arg)*)).unwrap()
+        crate::io::WriteFmt::write_fmt(&mut crate::io::FdWriter::stderr(), format_args!($($arg)*)).unwrap()
     }
 }
 
@@ -66,6 +79,91 @@ macro_rules! cursor_position {
     };
 }
 
+#[macro_export]
+macro_rules! cursor_position_to {
+    ($row:literal, $col:literal) => {
+        concat!("[", $row, ";", $col, "H").as_bytes()
+    };
+}
+
+#[macro_export]
+macro_rules! cursor_erase_to_eol {
+    () => {
+        b"[K"
+    };
+}
+
+#[macro_export]
+macro_rules! clear_screen {
+    () => {
+        b"[2J"
+    };
+}
+
+#[macro_export]
+macro_rules! reset_color {
+    () => {
+        b"[0m"
+    };
+}
+
+#[macro_export]
+macro_rules! bold {
+    () => {
+        b"[1m"
+    };
+}
+
+#[macro_export]
+macro_rules! italic {
+    () => {
+        b"[3m"
+    };
+}
+
+#[macro_export]
+macro_rules! dim {
+    () => {
+        b"[2m"
+    };
+}
+
+#[macro_export]
+macro_rules! normal_intensity {
+    () => {
+        b"[22m"
+    };
+}
+
+#[macro_export]
+macro_rules! enable_mouse {
+    () => {
+        b"[?1000h"
+    };
+}
+
+#[macro_export]
+macro_rules! disable_mouse {
+    () => {
+        b"[?1000l"
+    };
+}
+
+/// `OSC 0 ;`, the title up to the next BEL goes on the window.
+#[macro_export]
+macro_rules! set_title {
+    () => {
+        b"]0;"
+    };
+}
+
+#[macro_export]
+macro_rules! bell {
+    () => {
+        b""
+    };
+}
+
 #[macro_export]
 macro_rules! buffer_size {
     () => {
@@ -124,37 +222,160 @@ macro_rules! fg_color {
     (br_white) => {
         b"[97m"
     };
+
+    (256, $n:literal) => {
+        concat_bytes!(b"[38;5;", $n, b"m")
+    };
+    (rgb, $r:literal, $g:literal, $b:literal) => {
+        concat_bytes!(b"[38;2;", $r, b";", $g, b";", $b, b"m")
+    };
 }
 
+#[macro_export]
+macro_rules! bg_color {
+    (black) => {
+        b"[40m"
+    };
+    (red) => {
+        b"[41m"
+    };
+    (green) => {
+        b"[42m"
+    };
+    (yellow) => {
+        b"[43m"
+    };
+    (blue) => {
+        b"[44m"
+    };
+    (magenta) => {
+        b"[45m"
+    };
+    (cyan) => {
+        b"[46m"
+    };
+    (white) => {
+        b"[47m"
+    };
+
+    (br_black) => {
+        b"[100m"
+    };
+    (br_red) => {
+        b"[101m"
+    };
+    (br_green) => {
+        b"[102m"
+    };
+    (br_yellow) => {
+        b"[103m"
+    };
+    (br_blue) => {
+        b"[104m"
+    };
+    (br_magenta) => {
+        b"[105m"
+    };
+    (br_cyan) => {
+        b"[106m"
+    };
+    (br_white) => {
+        b"[107m"
+    };
+
+    (256, $n:literal) => {
+        concat_bytes!(b"[48;5;", $n, b"m")
+    };
+    (rgb, $r:literal, $g:literal, $b:literal) => {
+        concat_bytes!(b"[48;2;", $r, b";", $g, b";", $b, b"m")
+    };
+}
+
+static ON_EXIT_CALLED: AtomicBool = AtomicBool::new(false);
+/// Set once `TERMIOS` holds the mode to go back to; until then there is nothing to restore.
+static TERMIOS_SAVED: AtomicBool = AtomicBool::new(false);
+/// Off with `--no-alternate-screen`: the clock draws over the normal screen and leaves the cursor
+/// as it is.
+static ALTERNATE_SCREEN: AtomicBool = AtomicBool::new(true);
+
 #[inline(always)]
 fn on_exit() -> io::Result<()> {
-    FdWriter::stdout().write_all(concat_bytes!(restore_buffer!(), show_cursor!()))?;
+    restore_terminal(&mut FdWriter::stdout(), || {
+        #[allow(static_mut_refs)]
+        unsafe { nc::ioctl(io::STDIN, nc::TCSETS, TERMIOS.as_ptr() as _) }.map(drop)
+    })
+}
 
-    #[allow(static_mut_refs)]
-    unsafe {
-        nc::ioctl(io::STDIN, nc::TCSETS, TERMIOS.as_ptr() as _)?;
+/// Does the work of [`on_exit`] the first time only, as a signal and a panic can both get there,
+/// and not at all before the terminal mode was saved.
+fn restore_terminal(
+    writer: &mut impl io::Write,
+    restore_mode: impl FnOnce() -> io::Result<()>,
+) -> io::Result<()> {
+    if !TERMIOS_SAVED.load(Ordering::Acquire)
+        || ON_EXIT_CALLED
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+    {
+        return Ok(());
     }
-
-    Ok(())
+    if unsafe { TITLE } {
+        writer.write_all(concat_bytes!(set_title!(), bell!()))?;
+    }
+    writer.write_all(concat_bytes!(
+        disable_mouse!(),
+        reset_color!(),
+        normal_intensity!()
+    ))?;
+    // without the alternate screen the shell prompt comes back on the clock's screen, so clear it
+    writer.write_all(match ALTERNATE_SCREEN.load(Ordering::Relaxed) {
+        true => concat_bytes!(restore_buffer!(), show_cursor!()),
+        false => concat_bytes!(clear_screen!(), cursor_position!()),
+    })?;
+    restore_mode()
 }
 
-#[cfg(target_arch = "x86_64")]
-#[unsafe(naked)]
-extern "C" fn restorer() {
-    naked_asm!("mov rax, 0xf", "syscall")
+/// What the terminal is put into once, before the first frame and until [`on_exit`]: the alternate
+/// screen with the cursor hidden, and mouse reports if asked for.
+fn setup(writer: &mut impl io::Write, mouse: bool) -> io::Result<()> {
+    if ALTERNATE_SCREEN.load(Ordering::Relaxed) {
+        writer.write_all(concat_bytes!(set_buffer!(), hide_cursor!()))?;
+    }
+    if mouse {
+        writer.write_all(enable_mouse!())?;
+    }
+    Ok(())
 }
 
 struct MarginBuf {
     buf: [u8; 32],
     len: u8,
+    n: u16,
 }
 
 impl MarginBuf {
     fn slice(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
         unsafe { self.buf.get_unchecked(..self.len as _) }
     }
 
+    /// Whether the escape held is for some other count than `n`.
+    fn is_stale(&self, n: usize) -> bool {
+        self.n as usize != n || n != 0 && self.len == 0
+    }
+
+    /// Keeps the escape when it already moves `n` cells; each buffer only goes one way.
     fn cursor_move(&mut self, n: usize, direction: Direction) -> io::Result<()> {
+        if !self.is_stale(n) {
+            return Ok(());
+        }
+        self.n = n as _;
+        if n == 0 {
+            self.len = 0;
+            return Ok(());
+        }
         let mut writer = ArrayWriter::new(&mut self.buf);
         cursor_move(&mut writer, n as _, direction)?;
         self.len = writer.len as _;
@@ -162,68 +383,208 @@ impl MarginBuf {
     }
 }
 
-fn resize() -> io::Result<()> {
-    let winsz = MaybeUninit::<nc::winsize_t>::uninit();
+/// Takes the window size from the kernel, `false` if it has none or reports 0x0,
+/// as on serial lines and under some multiplexers.
+fn read_winsize() -> bool {
+    let mut winsz = nc::winsize_t::default();
+    match unsafe { nc::ioctl(io::STDIN, nc::TIOCGWINSZ, &raw mut winsz as _) } {
+        Ok(_) if winsz.ws_row != 0 && winsz.ws_col != 0 => {
+            #[allow(static_mut_refs)]
+            unsafe {
+                WINSIZE.write(winsz)
+            };
+            true
+        }
+        _ => false,
+    }
+}
+
+fn set_winsize(rows: u16, cols: u16) {
+    #[allow(static_mut_refs)]
+    let winsz = unsafe { WINSIZE.assume_init_mut() };
+    (winsz.ws_row, winsz.ws_col) = (rows, cols);
+}
+
+/// Returns the largest scale up to `max_scale`, at least 1, at which the face fits the window, and
+/// centers it there, or keeps it where the arrow keys put it as far as it still fits.
+fn resize((width, height): (u16, u16), view: &mut View, max_scale: u16) -> io::Result<u16> {
     #[allow(static_mut_refs)]
     unsafe {
-        nc::ioctl(io::STDIN, nc::TIOCGWINSZ, winsz.as_ptr() as _).unwrap_or_else(|e| exit(e as _));
-        let nc::winsize_t { ws_row, ws_col, .. } = winsz.assume_init_ref();
+        let nc::winsize_t { ws_row, ws_col, .. } = WINSIZE.assume_init_ref();
+        let scale = (ws_col / width).min(ws_row / height).clamp(1, max_scale);
+        let (width, height) = (width * scale, height * scale);
+        let (max_left, max_top) = (ws_col.saturating_sub(width), ws_row.saturating_sub(height));
+        if let Some((left, top)) = &mut view.position {
+            (*left, *top) = ((*left).min(max_left), (*top).min(max_top));
+        }
+        let (left, top) = view.position.unwrap_or((max_left / 2, max_top / 2));
 
         MARGIN_LEFT
             .assume_init_mut()
-            .cursor_move(((ws_col - 38) / 2) as _, Direction::Right)?;
+            .cursor_move(left as _, Direction::Right)?;
         MARGIN_TOP
             .assume_init_mut()
-            .cursor_move(((ws_row - 5) / 2) as _, Direction::Down)?;
-    };
-    Ok(())
-}
-
-fn set_signal_handler() {
-    extern "C" fn terminate(_: i32) {
-        _ = on_exit();
-        exit(0);
+            .cursor_move(top as _, Direction::Down)?;
+        MARGIN_BOTTOM
+            .assume_init_mut()
+            .cursor_move((top + height + 1) as _, Direction::Down)?;
+        Ok(scale)
     }
+}
 
+/// Blocks SIGWINCH, SIGINT and SIGTERM and returns a signalfd to read them from, so the event loop
+/// takes each between frames instead of a handler cutting into one half written.
+fn block_signals() -> io::Result<i32> {
     unsafe {
-        let sa = nc::sigaction_t {
-            sa_handler: terminate as *const () as _,
-            sa_flags: nc::SA_RESTORER,
-            sa_restorer: None,
-            ..Default::default()
+        let mask = nc::sigset_t {
+            sig: [[nc::SIGWINCH, nc::SIGINT, nc::SIGTERM]
+                .iter()
+                .fold(0, |mask, signal| mask | 1 << (signal - 1))],
         };
-        _ = nc::rt_sigaction(nc::SIGINT, Some(&sa), None);
-        _ = nc::rt_sigaction(nc::SIGTERM, Some(&sa), None);
-
-        let sa = nc::sigaction_t {
-            sa_handler: resize as *const () as _,
-            sa_flags: nc::SA_RESTORER | nc::SA_RESTART,
-            sa_restorer: Some(restorer),
-            sa_mask: nc::sigset_t {
-                sig: [1 << (nc::SIGWINCH) - 1],
-            },
-            ..Default::default()
-        };
-        _ = nc::rt_sigaction(nc::SIGWINCH, Some(&sa), None);
+        nc::rt_sigprocmask(nc::SIG_BLOCK, Some(&mask), None)?;
+        nc::signalfd4(-1, &mask, nc::O_CLOEXEC)
+    }
+}
+
+const VERSION: &[u8] = concat!("clock ", env!("CARGO_PKG_VERSION"), "\n").as_bytes();
+
+const HELP: &[u8] = b"usage: clock [OPTION]...
+
+  -1, --12h                 12-hour clock with an AM/PM label
+  -s, --hide-seconds        leave out the seconds
+      --seconds             show them after all, the later of the two wins
+  -d, --date                show the date under the time
+  -z OFFSET                 the time at OFFSET instead of the TZ one
+      --zone OFFSET[=LABEL] stack up to 4 labeled clocks, one per --zone
+      --grid RxC OFF:OFF... a grid of clocks, one offset per tile
+      --style STYLE         block, braun, ascii, binary or compact
+      --scale N|auto        grow the digits up to N times as large (auto: 8)
+      --color COLOR         a name, br_NAME, colorN or #rrggbb
+      --bg COLOR            fill the screen behind the clock with COLOR
+      --label TEXT          write TEXT under the clock
+      --shadow              draw the clock over a dim copy of itself
+      --seconds-color COLOR|dim|none
+      --timer HH:MM:SS      count down
+      --pomodoro [WORK[/BREAK]]
+                            alternate WORK and BREAK minutes (25/5)
+      --stopwatch           count up from zero
+      --alarm HH:MM         ring the bell at HH:MM
+      --plain               print the time as text instead of drawing it
+      --version             print the version
+      --features            list the io_uring operations the kernel has
+
+OFFSET is a fixed offset from UTC between -12:00 and +14:00: +HH:MM,
+-HH:MM, UTC, GMT or UTC+N.
+Zone names such as America/New_York need zoneinfo and aren't supported,
+and neither is daylight saving time.
+";
+
+/// The io_uring operations the clock can use, listed by `--features`.
+const URING_OPS: [(&[u8], u8); 9] = [
+    (b"read", io_uring::OpCode::IORING_OP_READ as _),
+    (b"read_fixed", io_uring::OpCode::IORING_OP_READ_FIXED as _),
+    (b"read_multishot", IORING_OP_READ_MULTISHOT),
+    (b"write", io_uring::OpCode::IORING_OP_WRITE as _),
+    (b"timeout", io_uring::OpCode::IORING_OP_TIMEOUT as _),
+    (
+        b"timeout_remove",
+        io_uring::OpCode::IORING_OP_TIMEOUT_REMOVE as _,
+    ),
+    (
+        b"link_timeout",
+        io_uring::OpCode::IORING_OP_LINK_TIMEOUT as _,
+    ),
+    (
+        b"provide_buffers",
+        io_uring::OpCode::IORING_OP_PROVIDE_BUFFERS as _,
+    ),
+    (b"ftruncate", IORING_OP_FTRUNCATE),
+];
+
+/// `+NAME` for each of [`URING_OPS`] the kernel supports, `-NAME` for the rest.
+fn print_features() -> io::Result<()> {
+    let ops = IoUring::new(1)?.probe_supported_ops()?;
+    let mut stdout = FdWriter::stdout();
+    for (name, op) in URING_OPS {
+        stdout.write_all(if ops[op as usize] { b"+" } else { b"-" })?;
+        stdout.write_all(name)?;
+        stdout.write_all(b"\n")?;
     }
+    Ok(())
 }
 
 static mut TERMIOS: MaybeUninit<nc::termios_t> = MaybeUninit::uninit();
+/// Seconds east of UTC, set once from the arguments.
+static mut TZ_OFFSET: i32 = 0;
+/// `--title` is on, so the title gets cleared on the way out.
+static mut TITLE: bool = false;
+static mut WINSIZE: MaybeUninit<nc::winsize_t> = MaybeUninit::uninit();
 static mut MARGIN_LEFT: MaybeUninit<MarginBuf> = MaybeUninit::uninit();
 static mut MARGIN_TOP: MaybeUninit<MarginBuf> = MaybeUninit::uninit();
+static mut MARGIN_BOTTOM: MaybeUninit<MarginBuf> = MaybeUninit::uninit();
+
+/// Whether `fd` is a terminal, going by `TCGETS`.
+fn is_tty(fd: i32) -> bool {
+    let mut termios = MaybeUninit::<nc::termios_t>::uninit();
+    unsafe { nc::ioctl(fd, nc::TCGETS, termios.as_mut_ptr() as _) }.is_ok()
+}
+
+/// `HH:MM:SS` and a newline, for when there is no terminal to draw on.
+fn print_once(writer: &mut impl io::Write, seconds: isize) -> io::Result<()> {
+    let [s, min, h] = draw::time(seconds.rem_euclid(24 * 3600));
+    for (i, n) in [h, min, s].into_iter().enumerate() {
+        if i != 0 {
+            writer.write_all(b":")?;
+        }
+        writer.write_u64_padded(n as _, 2, b'0')?;
+    }
+    writer.write_all(b"\n")
+}
+
+/// The window title for `seconds`, `HH:MM` as the clock shows it.
+fn write_title(buf: &mut [u8; 16], seconds: isize, format: ClockFormat) -> io::Result<usize> {
+    let [_, min, h] = draw::time(format.apply(seconds));
+    let [h1, h2, m1, m2] = [h / 10, h % 10, min / 10, min % 10].map(|d| b'0' + d as u8);
+    let mut writer = ArrayWriter::new(buf);
+    writer.write_all(set_title!())?;
+    writer.write_all(&[h1, h2, b':', m1, m2])?;
+    writer.write_all(bell!())?;
+    Ok(writer.len)
+}
 
 fn margin_left() -> &'static [u8] {
     #[allow(static_mut_refs)]
     unsafe { MARGIN_LEFT.assume_init_ref() }.slice()
 }
 
-fn margin_top() -> &'static [u8] {
+/// From the top of the screen to the line after the one below the clock.
+fn margin_bottom() -> &'static [u8] {
+    #[allow(static_mut_refs)]
+    unsafe { MARGIN_BOTTOM.assume_init_ref() }.slice()
+}
+
+fn margins() -> (u16, u16) {
+    #[allow(static_mut_refs)]
+    unsafe {
+        (
+            MARGIN_LEFT.assume_init_ref().n,
+            MARGIN_TOP.assume_init_ref().n,
+        )
+    }
+}
+
+fn tz_offset() -> i32 {
+    unsafe { TZ_OFFSET }
+}
+
+fn winsize() -> (u16, u16) {
     #[allow(static_mut_refs)]
-    unsafe { MARGIN_TOP.assume_init_ref() }.slice()
+    let winsz = unsafe { WINSIZE.assume_init_ref() };
+    (winsz.ws_row, winsz.ws_col)
 }
 
 #[repr(u8)]
-#[allow(unused)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum Direction {
     Up = b'A',
     Down = b'B',
@@ -232,170 +593,2362 @@ enum Direction {
 }
 
 fn cursor_move(writer: &mut impl io::Write, n: u64, direction: Direction) -> io::Result<()> {
+    if n == 0 {
+        return Ok(());
+    }
     writer.write_all(b"[")?;
     writer.write_u64(n)?;
     writer.write_all(&[direction as _][..])?;
     Ok(())
 }
 
-fn main() -> io::Result<()> {
-    let mut buf = MaybeUninit::<[u8; 1024]>::uninit();
-    let buf = unsafe { buf.assume_init_mut() };
-    let mut ctx = draw::Context::new(BufWriter::new(FdWriter::stdout(), buf));
+fn cursor_position_to(writer: &mut impl io::Write, row: u64, col: u64) -> io::Result<()> {
+    writer.write_all(b"[")?;
+    writer.write_u64(row)?;
+    writer.write_all(b";")?;
+    writer.write_u64(col)?;
+    writer.write_all(b"H")?;
+    Ok(())
+}
 
-    let get_time = || -> io::Result<isize> {
-        let mut time = MaybeUninit::uninit();
-        unsafe {
-            nc::time(time.assume_init_mut())?;
-            Ok(time.assume_init())
-        }
-    };
+const COLORS: [&[u8]; 8] = [
+    fg_color!(br_blue),
+    fg_color!(br_magenta),
+    fg_color!(br_cyan),
+    fg_color!(br_white),
+    fg_color!(br_black),
+    fg_color!(br_red),
+    fg_color!(br_green),
+    fg_color!(br_yellow),
+];
 
-    let seconds = Cell::new(get_time()?);
+/// What the keyboard can change while running.
+#[derive(Clone, Copy)]
+struct View {
+    /// Index into [`COLORS`]; `None` until `c` is pressed keeps the `--color` one.
+    color: Option<u8>,
+    format: ClockFormat,
+    style: Style,
+    seconds: bool,
+    paused: bool,
+    rainbow: bool,
+    blink: bool,
+    /// Margins picked with the arrow keys; `None` keeps the clock centered.
+    position: Option<(u16, u16)>,
+}
 
-    let mut redraw = || -> io::Result<()> {
-        ctx.writer.write_all(concat_bytes!(
-            restore_buffer!(),
-            set_buffer!(),
-            cursor_position!(),
-            fg_color!(br_blue),
-        ))?;
-        ctx.writer.write_all(margin_top())?;
-        let content = draw_time(seconds.get() + 8 * 3600);
-        ctx.draw(Some(margin_left()), || content)?;
-        ctx.writer.flush()?;
-        Ok(())
-    };
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            color: None,
+            format: ClockFormat::TwentyFourHour,
+            style: Style::Block,
+            seconds: true,
+            paused: false,
+            rainbow: false,
+            blink: false,
+            position: None,
+        }
+    }
+}
 
-    #[allow(static_mut_refs)]
-    unsafe {
-        nc::ioctl(io::STDIN, nc::TCGETS, TERMIOS.as_ptr() as _)?;
-        let mut termios = TERMIOS.assume_init_ref().clone();
-        termios.c_lflag &= !(nc::ECHO | nc::ICANON);
-        nc::ioctl(io::STDIN, nc::TCSETS, &raw const termios as _)?;
+impl View {
+    /// Returns whether `key` changed anything.
+    fn handle_key(&mut self, key: u8) -> bool {
+        match key {
+            b'c' => self.color = Some(self.color.map_or(1, |i| (i + 1) % COLORS.len() as u8)),
+            b't' => self.format = self.format.toggle(),
+            b'm' => self.style = self.style.next(),
+            b's' => self.seconds = !self.seconds,
+            b' ' => self.paused = !self.paused,
+            b'r' => self.rainbow = !self.rainbow,
+            b'b' => self.blink = !self.blink,
+            _ => return false,
+        }
+        true
     }
 
-    resize()?;
-    redraw()?;
-    set_signal_handler();
-    FdWriter::stdout().write_all(hide_cursor!())?;
+    /// Moves the clock one cell; [`resize`] keeps it on screen.
+    fn nudge(&mut self, direction: Direction) {
+        let (left, top) = self.position.unwrap_or_else(margins);
+        self.position = Some(match direction {
+            Direction::Up => (left, top.saturating_sub(1)),
+            Direction::Down => (left, top + 1),
+            Direction::Right => (left + 1, top),
+            Direction::Left => (left.saturating_sub(1), top),
+        });
+    }
 
-    #[repr(usize)]
-    enum Token {
-        Timeout = 1,
-        Read,
+    fn config(&self) -> Config {
+        Config {
+            format: self.format,
+            blink_colon: self.blink,
+            show_seconds: self.seconds,
+            ..Default::default()
+        }
     }
-    let ring = IoUring::new(2)?;
 
-    let mut input_buf = MaybeUninit::<[u8; 32]>::uninit();
-    ring.prepare_read(
-        io::STDIN as _,
-        unsafe { input_buf.assume_init_mut() },
-        Token::Read as _,
-    );
-    let duration = nc::timespec_t {
-        tv_sec: 1,
-        tv_nsec: 0,
-    };
-    ring.prepare_timeout(&duration, Token::Timeout as _, 1 << 6); // multishot
+    fn color(&self) -> Option<&'static [u8]> {
+        self.color.map(|i| COLORS[i as usize])
+    }
+}
 
-    ring.submit(2)?;
+/// Where `--screensaver` has drifted the clock to, and which way it is heading.
+struct ScreensaverState {
+    pos: (u16, u16),
+    vel: (i8, i8),
+}
 
-    fn wait(ring: &IoUring, cb: &mut impl FnMut() -> io::Result<()>) -> io::Result<()> {
-        loop {
-            match ring.wait() {
-                Ok(_) => break Ok(()),
-                Err(x) if x == nc::EINTR => cb()?,
-                Err(x) => break Err(x),
-            }
-        }
+impl ScreensaverState {
+    fn new(pos: (u16, u16)) -> Self {
+        // two columns a step, as cells are about twice as tall as wide
+        Self { pos, vel: (2, 1) }
     }
 
-    loop {
-        wait(&ring, &mut redraw)?;
-        let cqe = ring.complete();
-        match cqe.user_data {
-            x if x == Token::Timeout as _ => {
-                seconds.set(get_time()?);
-                redraw()?;
-            }
-            x if x == Token::Read as _ => {
-                if cqe.res == 1 && [b'', b'q'].contains(&unsafe { input_buf.assume_init_ref() }[0])
-                {
-                    break;
-                }
-                ring.prepare_read(
-                    io::STDIN as _,
-                    unsafe { input_buf.assume_init_mut() },
-                    Token::Read as _,
-                );
-            }
-            _ => return Err(nc::EIO),
-        }
-        ring.submit(1)?;
+    /// Moves one step, turning back from whichever edges the content would cross.
+    fn update(&mut self, ws_row: u16, ws_col: u16, content_h: u16, content_w: u16) {
+        let (left, dx) = bounce(self.pos.0, self.vel.0, ws_col.saturating_sub(content_w));
+        let (top, dy) = bounce(self.pos.1, self.vel.1, ws_row.saturating_sub(content_h));
+        (self.pos, self.vel) = ((left, top), (dx, dy));
     }
-    on_exit()
 }
 
-#[cfg_attr(not(test), unsafe(no_mangle))]
-extern "C" fn _start() -> ! {
-    exit(match main() {
-        Ok(_) => 0,
-        Err(e) => e as _,
-    });
+/// A step of `vel` from `pos`, reversed if it would leave `0..=max`.
+const fn bounce(pos: u16, vel: i8, max: u16) -> (u16, i8) {
+    let next = pos as i32 + vel as i32;
+    let vel = if next < 0 || next > max as i32 {
+        -vel
+    } else {
+        vel
+    };
+    let next = pos as i32 + vel as i32;
+    let next = if next < 0 {
+        0
+    } else if next > max as i32 {
+        max as i32
+    } else {
+        next
+    };
+    (next as u16, vel)
 }
 
-pub fn exit(status: usize) -> ! {
-    unsafe { nc::exit_group(status as _) };
+/// Columns and rows of the clock faces at scale 1.
+fn face_size(args: &args::Args, view: &View) -> (u16, u16) {
+    let layout = Layout::new(args, view);
+    match layout.stacked {
+        true => layout.fit(winsize().0).size(),
+        false => layout.size(),
+    }
 }
 
-#[cfg_attr(not(test), panic_handler)]
-pub fn panic(info: &PanicInfo) -> ! {
-    _ = on_exit();
-    if let Some(x) = info.location() {
-        eprint!("{}: ", x);
-    }
-    eprint!("{}\n", info.message());
-    exit(1)
+/// Whether a face of `(width, height)` fits a `(rows, cols)` window at scale 1.
+const fn fits((width, height): (u16, u16), (rows, cols): (u16, u16)) -> bool {
+    width <= cols && height <= rows
 }
 
-#[cfg_attr(not(test), global_allocator)]
-pub static GLOBAL_ALLOCATOR: GlobalAllocator = GlobalAllocator;
+/// The `--tz` faces in a row, [`Layout::GAP`] columns apart, under a row of labels if there are
+/// any, or with `--zone` one above another, each under its label and [`Layout::ROW_GAP`] rows
+/// apart. Without either it is the one clock face.
+#[derive(Clone, Copy)]
+struct Layout {
+    face: (u16, u16),
+    faces: u16,
+    label_rows: u16,
+    stacked: bool,
+}
 
-pub struct GlobalAllocator;
+impl Layout {
+    const GAP: u16 = 4;
+    const ROW_GAP: u16 = 1;
 
-unsafe impl GlobalAlloc for GlobalAllocator {
-    unsafe fn alloc(&self, _layout: core::alloc::Layout) -> *mut u8 {
-        null_mut()
+    fn new(args: &args::Args, view: &View) -> Self {
+        let mut face = match args.mode {
+            ClockMode::Wall => view.style.size(view.seconds),
+            ClockMode::Timer | ClockMode::Stopwatch => view.style.stopwatch_size(),
+        };
+        // the shadow sticks out a cell, the label takes a blank row and its own under the face
+        if view.style == Style::Block {
+            face.0 += args.shadow as u16;
+            face.1 += args.shadow as u16 + 2 * args.label.is_some() as u16;
+        }
+        Self {
+            face,
+            faces: (args.zones.len as u16).max(1),
+            label_rows: args.zones.has_labels() as _,
+            stacked: args.zones.stacked,
+        }
     }
 
-    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
-}
+    /// Drops the stacked faces that don't fit in `rows`, keeping at least the first.
+    fn fit(self, rows: u16) -> Self {
+        let step = self.face.1 + self.label_rows + Self::ROW_GAP;
+        let faces = match self.stacked {
+            true => ((rows + Self::ROW_GAP) / step).clamp(1, self.faces),
+            false => self.faces,
+        };
+        Self { faces, ..self }
+    }
 
-// restrict pointer
-#[cfg_attr(not(test), unsafe(no_mangle))]
-pub fn memcpy(dst: &mut u8, src: &u8, mut n: usize) -> *mut u8 {
-    let mut dst = dst as *mut u8;
-    let mut src = src as *const u8;
-    while n != 0 {
-        unsafe {
-            *dst = *src;
-            dst = dst.add(1);
-            src = src.add(1);
+    fn size(&self) -> (u16, u16) {
+        let (width, height) = self.face;
+        match self.stacked {
+            true => (
+                width,
+                self.faces * (height + self.label_rows) + (self.faces - 1) * Self::ROW_GAP,
+            ),
+            false => (
+                self.faces * width + (self.faces - 1) * Self::GAP,
+                height + self.label_rows,
+            ),
         }
-        n -= 1;
     }
-    dst
-}
 
-#[cfg_attr(not(test), unsafe(no_mangle))]
-pub fn memset(mut dst: *mut u8, chr: u8, mut n: usize) -> *mut u8 {
-    while n != 0 {
-        unsafe {
-            *dst = chr;
-            dst = dst.add(1);
+    /// Rows down and columns right from the margins to face `i` at `scale`.
+    fn offset(&self, i: u16, scale: u16) -> (u16, u16) {
+        match self.stacked {
+            true => (
+                i * (self.face.1 + self.label_rows + Self::ROW_GAP) * scale,
+                0,
+            ),
+            false => (0, i * (self.face.0 + Self::GAP) * scale),
         }
-        n -= 1;
     }
-    dst
+}
+
+fn monotonic() -> io::Result<nc::timespec_t> {
+    let mut now = nc::timespec_t::default();
+    unsafe { nc::clock_gettime(nc::CLOCK_MONOTONIC, &mut now) }?;
+    Ok(now)
+}
+
+fn realtime() -> io::Result<nc::timespec_t> {
+    let mut now = nc::timespec_t::default();
+    unsafe { nc::clock_gettime(nc::CLOCK_REALTIME, &mut now) }?;
+    Ok(now)
+}
+
+/// Whether the wall clock moved by more than a couple of seconds besides the time that passed,
+/// as after a suspend, which the monotonic clock sleeps through, or an NTP step.
+const fn clock_stepped(real_delta: i64, mono_delta: i64) -> bool {
+    (real_delta - mono_delta).abs() > 2_000_000_000
+}
+
+/// The first multiple of `interval` after `now`, so ticks land on the wall clock's
+/// own second boundaries however late the last one was handled.
+const fn next_tick(now: i64, interval: i64) -> i64 {
+    (now.div_euclid(interval) + 1) * interval
+}
+
+const fn nanos(t: &nc::timespec_t) -> i64 {
+    t.tv_sec as i64 * 1_000_000_000 + t.tv_nsec as i64
+}
+
+const fn timespec(nanos: i64) -> nc::timespec_t {
+    nc::timespec_t {
+        tv_sec: nanos.div_euclid(1_000_000_000) as _,
+        tv_nsec: nanos.rem_euclid(1_000_000_000) as _,
+    }
+}
+
+/// Which half of a `--pomodoro` cycle the timer counts down, and how many cycles began.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Pomodoro {
+    work: u64,
+    rest: u64,
+    resting: bool,
+    cycle: u16,
+}
+
+impl Pomodoro {
+    const fn new((work, rest): (u64, u64)) -> Self {
+        Self {
+            work,
+            rest,
+            resting: false,
+            cycle: 1,
+        }
+    }
+
+    /// Centiseconds the current phase lasts.
+    const fn total(&self) -> u64 {
+        (if self.resting { self.rest } else { self.work }) * 60 * 100
+    }
+
+    /// Moves on to the other phase, a new cycle after a break.
+    const fn advance(&mut self) {
+        self.cycle += self.resting as u16;
+        self.resting = !self.resting;
+    }
+
+    /// `WORK 1`, `BREAK 1` and so on, saying so while paused.
+    fn write_label(&self, writer: &mut impl io::Write, paused: bool) -> io::Result<()> {
+        writer.write_all(if self.resting { b"BREAK " } else { b"WORK " })?;
+        writer.write_u64(self.cycle as _)?;
+        if paused {
+            writer.write_all(b" - paused, press space")?;
+        }
+        Ok(())
+    }
+}
+
+/// Time taken since `--stopwatch` started, on `CLOCK_MONOTONIC`.
+struct Stopwatch {
+    start: nc::timespec_t,
+    paused_at: Option<nc::timespec_t>,
+    /// Time spent paused since `start`.
+    offset: nc::timespec_t,
+}
+
+impl Stopwatch {
+    fn new(now: nc::timespec_t) -> Self {
+        Self {
+            start: now,
+            paused_at: None,
+            offset: nc::timespec_t::default(),
+        }
+    }
+
+    fn elapsed_centiseconds(&self, now: &nc::timespec_t) -> u64 {
+        let end = self.paused_at.as_ref().unwrap_or(now);
+        let elapsed = nanos(end) - nanos(&self.start) - nanos(&self.offset);
+        (elapsed / 10_000_000).max(0) as _
+    }
+
+    /// Space and Enter pause and resume, `r` goes back to zero. Returns whether `key` was one
+    /// of them.
+    fn handle_key(&mut self, key: u8, now: nc::timespec_t) -> bool {
+        match (key, self.paused_at.take()) {
+            (b' ' | b'\n' | b'\r', Some(paused_at)) => {
+                self.offset = timespec(nanos(&self.offset) + nanos(&now) - nanos(&paused_at));
+            }
+            (b' ' | b'\n' | b'\r', None) => self.paused_at = Some(now),
+            (b'r', paused_at) => {
+                *self = Self {
+                    paused_at: paused_at.map(|_| now.clone()),
+                    ..Self::new(now)
+                }
+            }
+            (_, paused_at) => {
+                self.paused_at = paused_at;
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Whether `at` seconds into each `period` came by after `prev` and up to `now`.
+const fn crossed(prev: isize, now: isize, at: isize, period: isize) -> bool {
+    (now - at).div_euclid(period) != (prev - at).div_euclid(period)
+}
+
+/// Color changes in a chime or alarm flash, [`FLASH_INTERVAL`] apart.
+const FLASHES: u8 = 6;
+const FLASH_INTERVAL: nc::timespec_t = timespec(250_000_000);
+
+/// What a timer of `total` centiseconds shows after `elapsed`, and whether it ran out.
+const fn countdown(total: u64, elapsed: u64, overrun: bool) -> (u64, bool) {
+    match elapsed.checked_sub(total) {
+        Some(over) if overrun => (over, true),
+        Some(_) => (0, true),
+        None => (total - elapsed, false),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Key {
+    Esc,
+    Arrow(Direction),
+    /// An arrow with Shift held, `ESC[1;2A` and so on.
+    ShiftArrow(Direction),
+    /// An X10 mouse report: the button, then the 1-based column and row.
+    Mouse(u8, u16, u16),
+    /// A `CSI 18t` answer: the rows and columns of the window.
+    Size(u16, u16),
+    Byte(u8),
+}
+
+/// Splits one read from stdin into keys. Terminals write a CSI sequence in one go,
+/// so an ESC not followed by `[` is the Esc key itself.
+fn for_each_key(mut input: &[u8], mut f: impl FnMut(Key)) {
+    while let [first, rest @ ..] = input {
+        input = rest;
+        match (first, rest) {
+            (b'', [b'[', rest @ ..]) => {
+                // parameter bytes up to and including the final byte
+                let len = rest
+                    .iter()
+                    .position(|c| (0x40..=0x7e).contains(c))
+                    .map_or(rest.len(), |n| n + 1);
+                match rest[..len] {
+                    [b'A'] => f(Key::Arrow(Direction::Up)),
+                    [b'B'] => f(Key::Arrow(Direction::Down)),
+                    [b'C'] => f(Key::Arrow(Direction::Right)),
+                    [b'D'] => f(Key::Arrow(Direction::Left)),
+                    [b'1', b';', b'2', b'A'] => f(Key::ShiftArrow(Direction::Up)),
+                    [b'1', b';', b'2', b'B'] => f(Key::ShiftArrow(Direction::Down)),
+                    [b'1', b';', b'2', b'C'] => f(Key::ShiftArrow(Direction::Right)),
+                    [b'1', b';', b'2', b'D'] => f(Key::ShiftArrow(Direction::Left)),
+                    [.., b't'] => {
+                        if let Some((rows, cols)) = parse_size_report(&rest[..len]) {
+                            f(Key::Size(rows, cols));
+                        }
+                    }
+                    // X10 mouse reports carry three more bytes, each offset by 32
+                    [b'M'] if rest.len() >= 4 => {
+                        let [b, x, y] = [rest[1], rest[2], rest[3]].map(|c| c.wrapping_sub(32));
+                        f(Key::Mouse(b, x as _, y as _));
+                        input = &rest[4..];
+                        continue;
+                    }
+                    _ => {}
+                }
+                input = &rest[len..];
+            }
+            (b'', _) => f(Key::Esc),
+            (&byte, _) => f(Key::Byte(byte)),
+        }
+    }
+}
+
+/// Which way a click at `col` turns the UTC offset: back left of the clock, forward right of it.
+fn click_step(col: u16, left: u16, width: u16) -> i32 {
+    match col.saturating_sub(1) {
+        c if c < left => -3600,
+        c if c >= left + width => 3600,
+        _ => 0,
+    }
+}
+
+/// How a Shift+arrow turns the UTC offset: half an hour sideways, an hour up or down.
+const fn arrow_tz_step(direction: Direction) -> i32 {
+    match direction {
+        Direction::Up => 3600,
+        Direction::Down => -3600,
+        Direction::Right => 1800,
+        Direction::Left => -1800,
+    }
+}
+
+/// Moves `offset` by `step`, going round from UTC+14 to UTC-12 and back.
+const fn cycle_tz_offset(offset: i32, step: i32) -> i32 {
+    let offset = offset + step;
+    if offset > args::MAX_TZ_OFFSET {
+        args::MIN_TZ_OFFSET
+    } else if offset < args::MIN_TZ_OFFSET {
+        args::MAX_TZ_OFFSET
+    } else {
+        offset
+    }
+}
+
+/// User data of the requests the event loop submits.
+#[repr(usize)]
+enum Token {
+    Timeout = 1,
+    Read,
+    Signal,
+    Write,
+    Provide,
+    ReadTimeout,
+    Loadavg,
+    TimeoutRemove,
+    Flash,
+}
+
+/// What the completions of one batch ask of the event loop.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+struct Action {
+    timeout: bool,
+    winch: bool,
+    changed: bool,
+    flashed: bool,
+    quit: bool,
+    /// A key or click came in, as opposed to a size report.
+    input: bool,
+    /// The stdin read ended and has to be prepared again.
+    rearm: bool,
+    /// A bit set for each pool buffer handed back by a multishot read.
+    returned: u8,
+}
+
+impl Action {
+    fn merge(&mut self, other: Action) {
+        self.timeout |= other.timeout;
+        self.winch |= other.winch;
+        self.changed |= other.changed;
+        self.flashed |= other.flashed;
+        self.quit |= other.quit;
+        self.input |= other.input;
+        self.rearm |= other.rearm;
+        self.returned |= other.returned;
+    }
+}
+
+/// What [`handle_cqe`] reads and changes, borrowed from the event loop for one batch.
+struct State<'a, 'w> {
+    args: &'a args::Args,
+    view: &'a mut View,
+    stopwatch: &'a mut Option<Stopwatch>,
+    /// The scale the face is drawn at, to tell where a click landed.
+    scale: u16,
+    input: &'a IoUringReadHandle<'a>,
+    input_pool: &'a [[u8; 32]],
+    /// The `struct signalfd_siginfo` last read.
+    siginfo_buf: &'a [u8],
+    loadavg_buf: &'a [u8],
+    load: &'a Cell<Option<u32>>,
+    writer: &'a mut Output<'w>,
+}
+
+/// Acts on the keys, mouse reports and size reports in `input`.
+fn handle_input(input: &[u8], state: &mut State) -> io::Result<Action> {
+    let mut action = Action::default();
+    let mut error = Ok(());
+    let State {
+        args,
+        view,
+        stopwatch,
+        scale,
+        ..
+    } = state;
+    for_each_key(input, |key| {
+        action.input |= !matches!(key, Key::Size(..));
+        match key {
+            Key::Esc | Key::Byte(b'q') => action.quit = true,
+            Key::Arrow(direction) => {
+                view.nudge(direction);
+                action.changed = true;
+            }
+            Key::ShiftArrow(direction) => {
+                let step = arrow_tz_step(direction);
+                unsafe { TZ_OFFSET = cycle_tz_offset(TZ_OFFSET, step) };
+                action.changed = true;
+            }
+            // button 0 is a left press, the release reports 3
+            Key::Mouse(0, col, row) if args.grid.is_none() && args.zones.len == 0 => {
+                let (left, top) = margins();
+                let (width, height) = face_size(args, view);
+                let (width, height) = (width * *scale, height * *scale);
+                let step = click_step(col, left, width);
+                if step != 0 && (top + 1..=top + height).contains(&row) {
+                    unsafe { TZ_OFFSET = cycle_tz_offset(TZ_OFFSET, step) };
+                    action.changed = true;
+                }
+            }
+            Key::Mouse(..) => {}
+            Key::Size(rows, cols) => {
+                set_winsize(rows, cols);
+                action.changed = true;
+            }
+            Key::Byte(key) => match stopwatch {
+                Some(stopwatch) if matches!(key, b' ' | b'\n' | b'\r' | b'r') => {
+                    match monotonic() {
+                        Ok(now) => action.changed |= stopwatch.handle_key(key, now),
+                        Err(e) => error = Err(e),
+                    }
+                }
+                // color and rainbow keys do nothing under NO_COLOR
+                _ if args.colors.no_color && matches!(key, b'c' | b'r') => {}
+                _ => action.changed |= view.handle_key(key),
+            },
+        }
+    });
+    error?;
+    Ok(action)
+}
+
+fn handle_cqe(cqe: &nc::io_uring_cqe_t, state: &mut State) -> io::Result<Action> {
+    let mut action = Action::default();
+    match (cqe.user_data, cqe.result()) {
+        (x, Ok(_) | Err(nc::ETIME)) if x == Token::Timeout as _ => action.timeout = true,
+        (x, Err(nc::ECANCELED)) if x == Token::Timeout as _ => {}
+        (x, _) if x == Token::TimeoutRemove as _ => {}
+        (x, _) if x == Token::Flash as _ => action.flashed = true,
+        (x, Ok(n)) if x == Token::Read as _ => {
+            let (pool, handle) = (state.input_pool, state.input);
+            let input = match cqe.buffer_id() {
+                Some(id) => {
+                    action.returned |= 1 << id;
+                    &pool[id as usize][..n as _]
+                }
+                None => handle.result(cqe)?,
+            };
+            // an empty read is EOF on stdin, nothing left to wait for
+            action.quit |= input.is_empty();
+            action.merge(handle_input(input, state)?);
+            action.rearm |= !cqe.has_more();
+        }
+        // the pool ran dry or the linked timeout fired, either ends the read
+        (x, Err(nc::ENOBUFS | nc::ECANCELED)) if x == Token::Read as _ => action.rearm = true,
+        (x, _) if x == Token::ReadTimeout as _ => {}
+        // ssi_signo comes first; SIGINT and SIGTERM end the loop like `q`
+        (x, Ok(_)) if x == Token::Signal as _ => {
+            match state
+                .siginfo_buf
+                .first_chunk()
+                .map(|signo| u32::from_ne_bytes(*signo))
+            {
+                Some(signo) if signo == nc::SIGWINCH as u32 => action.winch = true,
+                _ => action.quit = true,
+            }
+        }
+        (x, Ok(_)) if x == Token::Provide as _ => {}
+        (x, Ok(n)) if x == Token::Loadavg as _ => {
+            state.load.set(parse_loadavg(&state.loadavg_buf[..n as _]));
+        }
+        (x, _) if x == Token::Write as _ => state.writer.complete(cqe)?,
+        (_, Err(e)) => return Err(e),
+        _ => return Err(nc::EIO),
+    }
+    Ok(action)
+}
+
+/// The 1-minute load average out of `/proc/loadavg`, times 100.
+fn parse_loadavg(s: &[u8]) -> Option<u32> {
+    let load = &s[..s.iter().position(|&c| c == b' ')?];
+    let (int, frac) = load.split_at(load.iter().position(|&c| c == b'.')?);
+    let frac = match &frac[1..] {
+        [a, b, ..] => [*a, *b],
+        [a] => [*a, b'0'],
+        [] => [b'0'; 2],
+    };
+    let int = args::parse_u64(int)?;
+    let frac = args::parse_u64(&frac)?;
+    (int * 100 + frac).try_into().ok()
+}
+
+// `nc::openat` wants an allocator for the path
+fn open_loadavg() -> io::Result<i32> {
+    let path = c"/proc/loadavg";
+    let flags = nc::O_RDONLY | nc::O_CLOEXEC;
+    let fd = unsafe {
+        nc::syscalls::syscall4(
+            nc::SYS_OPENAT,
+            nc::AT_FDCWD as _,
+            path.as_ptr() as _,
+            flags as _,
+            0,
+        )
+    }?;
+    Ok(fd as _)
+}
+
+const fn load_color(load: u32) -> &'static [u8] {
+    match load {
+        ..100 => fg_color!(br_green),
+        100..=400 => fg_color!(br_yellow),
+        _ => fg_color!(br_red),
+    }
+}
+
+/// `inks` are what the hours and minutes and then the seconds are drawn in, switched between
+/// before each glyph so a frame that only redraws the seconds can't leave the rest in theirs.
+fn draw_clock(
+    ctx: &mut draw::Context<impl io::Write>,
+    view: &View,
+    position: (u64, u64),
+    seconds: isize,
+    truecolor: bool,
+    inks: Option<(Ink, Ink)>,
+) -> io::Result<()> {
+    let inks = inks.filter(|_| !view.rainbow);
+    let first_seconds = view.style.first_seconds_glyph();
+    // the label follows the seconds but goes with the rest
+    let colors = |i| match inks {
+        Some((primary, secondary)) => Some(if i < first_seconds || i == draw::LABEL {
+            primary
+        } else {
+            secondary
+        }),
+        None => (view.rainbow && i != draw::LABEL)
+            .then(|| Ink::Color(color::rainbow(seconds, i, truecolor))),
+    };
+    let label = view.format.label(seconds);
+    let config = view.config();
+    let (position, len) = (
+        Some(position),
+        view.style.visible_glyphs(config.show_seconds),
+    );
+    match view.style {
+        Style::Block => {
+            let content = draw_time(seconds, config);
+            ctx.draw_colored(
+                position,
+                None,
+                || content[..len].iter().copied(),
+                colors,
+                label,
+            )
+        }
+        Style::Ascii => {
+            let content = draw_time(seconds, config).map(draw::Ascii::new);
+            ctx.draw_colored(
+                position,
+                None,
+                || content[..len].iter().copied(),
+                colors,
+                label,
+            )
+        }
+        Style::Braun => {
+            let content = draw_time_braun_style(seconds, config);
+            ctx.draw_colored(
+                position,
+                None,
+                || content[..len].iter().copied(),
+                colors,
+                label,
+            )
+        }
+        Style::Binary => {
+            let content = draw::draw_binary(seconds, config);
+            ctx.draw_colored(
+                position,
+                None,
+                || content[..len].iter().copied(),
+                colors,
+                label,
+            )
+        }
+        Style::Compact => {
+            let content = draw::draw_time_compact(seconds, config);
+            ctx.draw_colored(
+                position,
+                None,
+                || content[..len].iter().copied(),
+                colors,
+                label,
+            )
+        }
+    }?;
+    match inks {
+        Some((primary, _)) => color::write_ink(&mut ctx.writer, primary),
+        None => Ok(()),
+    }
+}
+
+fn draw_stopwatch(
+    ctx: &mut draw::Context<impl io::Write>,
+    view: &View,
+    position: (u64, u64),
+    centiseconds: u64,
+    truecolor: bool,
+) -> io::Result<()> {
+    let seconds = (centiseconds / 100) as isize;
+    let colors = |i| {
+        view.rainbow
+            .then(|| Ink::Color(color::rainbow(seconds, i, truecolor)))
+    };
+    match view.style {
+        Style::Block | Style::Binary => {
+            let content = draw::draw_stopwatch(centiseconds);
+            ctx.draw_colored(Some(position), None, || content, colors, None)
+        }
+        Style::Ascii => {
+            let content = draw::draw_stopwatch(centiseconds).map(draw::Ascii::new);
+            ctx.draw_colored(Some(position), None, || content, colors, None)
+        }
+        Style::Braun => {
+            let content = draw::draw_stopwatch_braun_style(centiseconds);
+            ctx.draw_colored(Some(position), None, || content, colors, None)
+        }
+        Style::Compact => {
+            let content = draw::draw_stopwatch_compact(centiseconds);
+            ctx.draw_colored(Some(position), None, || content, colors, None)
+        }
+    }
+}
+
+fn draw_grid(
+    ctx: &mut draw::Context<impl io::Write>,
+    view: &View,
+    grid: &args::Grid,
+    seconds: isize,
+    truecolor: bool,
+    inks: Option<(Ink, Ink)>,
+) -> io::Result<()> {
+    let (width, height) = view.style.size(view.seconds);
+    let (ws_row, ws_col) = winsize();
+    let cell_h = ws_row / grid.rows as u16;
+    let cell_w = ws_col / grid.cols as u16;
+    for (i, &offset) in grid.offsets[..grid.len()].iter().enumerate() {
+        let (row, col) = (i as u16 / grid.cols as u16, i as u16 % grid.cols as u16);
+        let top = row * cell_h + cell_h.saturating_sub(height) / 2;
+        let left = col * cell_w + cell_w.saturating_sub(width) / 2;
+        let position = (top as u64 + 1, left as u64 + 1);
+        let seconds = seconds + offset as isize;
+        draw_clock(ctx, view, position, seconds, truecolor, inks)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_cursor_move_zero() {
+    let mut buf = [0; 8];
+    let mut writer = ArrayWriter::new(&mut buf);
+    cursor_move(&mut writer, 0, Direction::Right).unwrap();
+    assert_eq!(writer.len, 0);
+    cursor_move(&mut writer, 3, Direction::Down).unwrap();
+    assert_eq!(buf[..4], *b"\x1b[3B");
+}
+
+#[test]
+fn test_margin_buf() {
+    // as the zeroed statics start out
+    let mut margin = MarginBuf {
+        buf: [0; 32],
+        len: 0,
+        n: 0,
+    };
+    assert!(!margin.is_stale(0) && margin.slice().is_empty());
+    assert!(margin.is_stale(5));
+    margin.cursor_move(5, Direction::Right).unwrap();
+    assert_eq!(margin.slice(), b"\x1b[5C");
+    assert!(!margin.is_stale(5) && margin.is_stale(6));
+    margin.cursor_move(0, Direction::Right).unwrap();
+    assert!(margin.slice().is_empty());
+}
+
+#[test]
+fn test_seconds_ink() {
+    let mut buf = [0; 8192];
+    let mut ctx = draw::Context::new(ArrayWriter::new(&mut buf));
+    let view = View::default();
+    let inks = Some((Ink::Sgr(normal_intensity!()), Ink::Sgr(dim!())));
+    let mut ends = [0; 3];
+    for (end, seconds) in ends.iter_mut().zip([59, 60, 61]) {
+        draw_clock(
+            &mut ctx,
+            &view,
+            (1, 1),
+            12 * 3600 + 34 * 60 + seconds,
+            false,
+            inks,
+        )
+        .unwrap();
+        *end = ctx.writer.len;
+    }
+    let frame = |i: usize| {
+        let frame = &buf[i.checked_sub(1).map_or(0, |i| ends[i])..ends[i]];
+        let find = |sgr: &[u8]| frame.windows(sgr.len()).position(|w| w == sgr);
+        (
+            find(b"\x1b[22m"),
+            find(b"\x1b[2m"),
+            frame.ends_with(b"\x1b[22m"),
+        )
+    };
+    // dim for the seconds, back to normal for the minutes and after the face
+    let (normal, dim, restored) = frame(0);
+    assert!(normal < dim && restored);
+    // a new minute undims before its digits, a tick that only redraws the seconds still dims them
+    assert!(matches!(frame(1), (Some(0), Some(_), true)));
+    assert!(matches!(frame(2), (_, Some(0), true)));
+}
+
+#[test]
+fn test_view_handle_key() {
+    let mut view = View::default();
+    assert!(!view.handle_key(b'x'));
+    for _ in 0..COLORS.len() - 1 {
+        assert!(view.handle_key(b'c'));
+    }
+    assert_eq!(view.color(), Some(&fg_color!(br_yellow)[..]));
+    view.handle_key(b'c');
+    assert_eq!(view.color(), Some(&fg_color!(br_blue)[..]));
+    view.handle_key(b's');
+    view.handle_key(b' ');
+    view.handle_key(b'r');
+    view.handle_key(b'm');
+    assert_eq!(view.style, Style::Braun);
+    assert!(
+        !view.seconds && view.paused && view.format == ClockFormat::TwentyFourHour && view.rainbow
+    );
+}
+
+#[test]
+fn test_stopwatch() {
+    let at = |cs: i64| timespec(1_000_000_000_000 + cs * 10_000_000);
+    let mut stopwatch = Stopwatch::new(at(0));
+    assert_eq!(stopwatch.elapsed_centiseconds(&at(250)), 250);
+    assert!(stopwatch.handle_key(b' ', at(300)));
+    assert_eq!(stopwatch.elapsed_centiseconds(&at(900)), 300);
+    assert!(stopwatch.handle_key(b'\n', at(1000)));
+    assert_eq!(stopwatch.elapsed_centiseconds(&at(1050)), 350);
+    // 59:59.99 and one more, 7 s of which were spent paused
+    let end = 700 + 59 * 6000 + 5999;
+    assert_eq!(stopwatch.elapsed_centiseconds(&at(end)), 359_999);
+    assert_eq!(stopwatch.elapsed_centiseconds(&at(end + 1)), 360_000);
+    let glyphs = draw::draw_stopwatch(360_000).map(|g| g as *const _);
+    assert_eq!(
+        glyphs[..8],
+        draw_time(3600, Config::default()).map(|g| g as *const _)
+    );
+    let glyphs = draw::draw_stopwatch(100 * 360_000).map(|g| g as *const _);
+    assert_eq!(glyphs, draw::draw_stopwatch(0).map(|g| g as *const _));
+    assert!(!stopwatch.handle_key(b'x', at(end)));
+    assert!(stopwatch.handle_key(b'r', at(end)));
+    assert_eq!(stopwatch.elapsed_centiseconds(&at(end + 42)), 42);
+    stopwatch.handle_key(b' ', at(end + 42));
+    stopwatch.handle_key(b'r', at(end + 50));
+    assert_eq!(stopwatch.elapsed_centiseconds(&at(end + 99)), 0);
+}
+
+#[test]
+fn test_next_tick() {
+    let second = 1_000_000_000;
+    assert_eq!(next_tick(0, second), second);
+    assert_eq!(next_tick(5 * second + 999_999_999, second), 6 * second);
+    assert_eq!(next_tick(5 * second, second), 6 * second);
+    assert_eq!(next_tick(123 * second + 1, 60 * second), 180 * second);
+    assert_eq!(next_tick(250_000_001, second / 4), 500_000_000);
+}
+
+#[test]
+fn test_clock_stepped() {
+    let second = 1_000_000_000;
+    assert!(!clock_stepped(second, second));
+    // slewed or a late tick, still the same clock
+    assert!(!clock_stepped(second + second / 2, second));
+    assert!(!clock_stepped(0, 2 * second));
+    // an hour asleep, and a step back
+    assert!(clock_stepped(3600 * second, second));
+    assert!(clock_stepped(-5 * second, second));
+}
+
+#[test]
+fn test_fits() {
+    let face = (53, 5);
+    assert!(fits(face, (24, 80)));
+    assert!(fits(face, (5, 53)));
+    assert!(!fits(face, (24, 52)));
+    assert!(!fits(face, (4, 80)));
+    assert!(!fits(face, (0, 0)));
+}
+
+#[test]
+fn test_screensaver_bounce() {
+    // a 40x7 face on 80x24 may sit anywhere in 0..=40, 0..=17
+    let step = |pos, vel| {
+        let mut saver = ScreensaverState { pos, vel };
+        saver.update(24, 80, 7, 40);
+        (saver.pos, saver.vel)
+    };
+    assert_eq!(step((10, 5), (2, 1)), ((12, 6), (2, 1)));
+    assert_eq!(step((38, 5), (2, 1)), ((40, 6), (2, 1)));
+    // right, left, bottom and top edges
+    assert_eq!(step((39, 5), (2, 1)), ((37, 6), (-2, 1)));
+    assert_eq!(step((1, 5), (-2, 1)), ((3, 6), (2, 1)));
+    assert_eq!(step((10, 17), (2, 1)), ((12, 16), (2, -1)));
+    assert_eq!(step((10, 0), (2, -1)), ((12, 1), (2, 1)));
+    // corners turn both ways at once
+    assert_eq!(step((40, 17), (2, 1)), ((38, 16), (-2, -1)));
+    assert_eq!(step((0, 0), (-2, -1)), ((2, 1), (2, 1)));
+    assert_eq!(step((0, 17), (-2, 1)), ((2, 16), (2, -1)));
+    assert_eq!(step((40, 0), (2, -1)), ((38, 1), (-2, 1)));
+    // no room to move, or left outside by a shrinking window
+    assert_eq!(step((0, 0), (2, 1)), ((2, 1), (2, 1)));
+    let mut saver = ScreensaverState::new((70, 20));
+    saver.update(7, 40, 7, 40);
+    assert_eq!((saver.pos, saver.vel), ((0, 0), (-2, -1)));
+}
+
+#[test]
+fn test_pomodoro() {
+    let mut pomodoro = Pomodoro::new((25, 5));
+    assert_eq!(pomodoro.total(), 25 * 60 * 100);
+    pomodoro.advance();
+    assert_eq!((pomodoro.resting, pomodoro.cycle), (true, 1));
+    assert_eq!(pomodoro.total(), 5 * 60 * 100);
+    pomodoro.advance();
+    assert_eq!((pomodoro.resting, pomodoro.cycle), (false, 2));
+    let mut buf = [0; 32];
+    let mut writer = ArrayWriter::new(&mut buf);
+    pomodoro.write_label(&mut writer, false).unwrap();
+    let len = writer.len;
+    assert_eq!(&buf[..len], b"WORK 2");
+    pomodoro.advance();
+    let mut writer = ArrayWriter::new(&mut buf);
+    pomodoro.write_label(&mut writer, true).unwrap();
+    let len = writer.len;
+    assert_eq!(&buf[..len], b"BREAK 2 - paused, press space");
+}
+
+#[test]
+fn test_countdown() {
+    assert_eq!(countdown(1500, 0, false), (1500, false));
+    assert_eq!(countdown(1500, 1499, false), (1, false));
+    assert_eq!(countdown(1500, 1500, false), (0, true));
+    assert_eq!(countdown(1500, 1742, false), (0, true));
+    assert_eq!(countdown(1500, 1742, true), (242, true));
+}
+
+#[test]
+fn test_layout() {
+    let mut args = args::Args::default();
+    let view = View::default();
+    assert_eq!(face_size(&args, &view), Style::Block.size(true));
+    args.zones.push(b"UTC+0").unwrap();
+    args.zones.push(b"-05:00=EST").unwrap();
+    let layout = Layout::new(&args, &view);
+    assert_eq!(layout.size(), (2 * 39 + 4, 6));
+    assert_eq!(layout.offset(1, 2), (0, 2 * (39 + 4)));
+    assert_eq!(layout.fit(5).faces, 2);
+    args.zones.push(b"+09:00").unwrap();
+    args.zones.stacked = true;
+    let layout = Layout::new(&args, &view);
+    assert_eq!(layout.size(), (39, 3 * 6 + 2));
+    assert_eq!(layout.offset(2, 1), (2 * 7, 0));
+    assert_eq!(layout.fit(20).faces, 3);
+    assert_eq!(layout.fit(19).faces, 2);
+    assert_eq!(layout.fit(3).faces, 1);
+}
+
+#[test]
+fn test_write_title() {
+    let mut buf = [0; 16];
+    let len = write_title(
+        &mut buf,
+        13 * 3600 + 5 * 60 + 59,
+        ClockFormat::TwentyFourHour,
+    )
+    .unwrap();
+    assert_eq!(buf[..len], *b"\x1b]0;13:05\x07");
+    let len = write_title(&mut buf, 13 * 3600, ClockFormat::TwelveHour).unwrap();
+    assert_eq!(buf[..len], *b"\x1b]0;01:00\x07");
+}
+
+#[test]
+fn test_extended_colors() {
+    assert_eq!(fg_color!(256, b"208"), b"\x1b[38;5;208m");
+    assert_eq!(bg_color!(256, b"16"), b"\x1b[48;5;16m");
+    assert_eq!(
+        fg_color!(rgb, b"255", b"128", b"0"),
+        b"\x1b[38;2;255;128;0m"
+    );
+    assert_eq!(bg_color!(rgb, b"0", b"0", b"0"), b"\x1b[48;2;0;0;0m");
+    assert_eq!(
+        concat_bytes!(fg_color!(256, b"208"), bg_color!(rgb, b"0", b"0", b"0")),
+        b"\x1b[38;5;208m\x1b[48;2;0;0;0m"
+    );
+    assert_eq!(bg_color!(br_blue), b"\x1b[104m");
+    assert_eq!(reset_color!(), b"\x1b[0m");
+    assert_eq!(
+        concat_bytes!(bold!(), dim!(), italic!()),
+        b"\x1b[1m\x1b[2m\x1b[3m"
+    );
+    let mut buf = [0; draw::COLOR_SEQUENCE_SISE];
+    let len = color::Color::Rgb {
+        r: 255,
+        g: 128,
+        b: 0,
+    }
+    .ansi_sequence_fg(&mut buf);
+    assert_eq!(buf[..len], *fg_color!(rgb, b"255", b"128", b"0"));
+}
+
+#[test]
+fn test_crossed() {
+    let hour = 3600;
+    assert!(crossed(10 * hour - 1, 10 * hour, 0, hour));
+    assert!(!crossed(10 * hour, 10 * hour + 1, 0, hour));
+    // a tick that skips the exact second still counts
+    assert!(crossed(10 * hour - 1, 10 * hour + 1, 0, hour));
+    // 23:59:59 to 00:00:00 of the next day, and 11:59:59 AM to 12:00:00 PM, which the 12-hour
+    // face shows as 11 to 12 but is the same second count
+    assert!(crossed(24 * hour - 1, 24 * hour, 0, hour));
+    assert!(crossed(12 * hour - 1, 12 * hour, 0, hour));
+    assert!(!crossed(12 * hour - 2, 12 * hour - 1, 0, hour));
+    let alarm = 7 * hour + 30 * 60;
+    assert!(crossed(alarm - 60, alarm, alarm, 24 * hour));
+    assert!(crossed(
+        24 * hour + alarm - 1,
+        24 * hour + alarm,
+        alarm,
+        24 * hour
+    ));
+    assert!(!crossed(alarm, alarm + 3 * hour, alarm, 24 * hour));
+}
+
+#[test]
+fn test_print_once() {
+    let mut buf = [0; 32];
+    let mut writer = ArrayWriter::new(&mut buf);
+    print_once(&mut writer, 9 * 3600 + 5 * 60 + 7).unwrap();
+    print_once(&mut writer, -1).unwrap();
+    let len = writer.len;
+    assert_eq!(buf[..len], *b"09:05:07\n23:59:59\n");
+}
+
+#[test]
+fn test_restore_terminal_once() {
+    let mut buf = [0; 64];
+    let mut writer = ArrayWriter::new(&mut buf);
+    let restored = Cell::new(0);
+    // a failure before the terminal was touched leaves it alone
+    restore_terminal(&mut writer, || Err(nc::EIO)).unwrap();
+    assert_eq!(writer.len, 0);
+    TERMIOS_SAVED.store(true, Ordering::Release);
+    for _ in 0..2 {
+        restore_terminal(&mut writer, || {
+            restored.set(restored.get() + 1);
+            Ok(())
+        })
+        .unwrap();
+    }
+    assert_eq!(restored.get(), 1);
+    let len = writer.len;
+    assert_eq!(
+        buf[..len],
+        *b"\x1b[?1000l\x1b[0m\x1b[22m\x1b[?1049l\x1b[?25h"
+    );
+}
+
+#[test]
+fn test_no_alternate_screen() {
+    let mut fds = [0; 2];
+    unsafe { nc::pipe2(&mut fds, nc::O_CLOEXEC) }.unwrap();
+    let child = unsafe { io::fork_keeping(fds[1]) };
+    // a copy of the process, so its statics can be changed without the other tests seeing
+    if child.unwrap() == 0 {
+        ALTERNATE_SCREEN.store(false, Ordering::Relaxed);
+        ON_EXIT_CALLED.store(false, Ordering::Relaxed);
+        TERMIOS_SAVED.store(true, Ordering::Relaxed);
+        let mut stdout = FdWriter::new(fds[1]);
+        let result =
+            setup(&mut stdout, false).and_then(|_| restore_terminal(&mut stdout, || Ok(())));
+        unsafe { nc::exit_group(result.is_err() as _) };
+    }
+    unsafe { nc::close(fds[1]) }.unwrap();
+    let mut buf = [0; 64];
+    let mut len = 0;
+    while let n @ 1.. = unsafe { nc::read(fds[0], &mut buf[len..]) }.unwrap() {
+        len += n as usize;
+    }
+    assert_eq!(buf[..len], *b"\x1b[?1000l\x1b[0m\x1b[22m\x1b[2J\x1b[H");
+    let mut status = 0;
+    unsafe { nc::wait4(child.unwrap(), Some(&mut status), 0, None) }.unwrap();
+    assert_eq!(status, 0);
+    unsafe { nc::close(fds[0]) }.unwrap();
+    let mut buf = [0; 32];
+    let mut writer = io::ArrayWriter::new(&mut buf);
+    setup(&mut writer, true).unwrap();
+    let len = writer.len;
+    assert_eq!(buf[..len], *b"\x1b[?1049h\x1b[?25l\x1b[?1000h");
+}
+
+#[test]
+fn test_parse_loadavg() {
+    assert_eq!(parse_loadavg(b"0.52 0.58 0.59 1/189 12345\n"), Some(52));
+    assert_eq!(parse_loadavg(b"12.05 8.00 4.10 3/420 999\n"), Some(1205));
+    assert_eq!(parse_loadavg(b"1.5 1.0"), Some(150));
+    assert_eq!(parse_loadavg(b"x.52 0.58"), None);
+    assert_eq!(parse_loadavg(b"052"), None);
+    assert_eq!(parse_loadavg(b"052 1.00"), None);
+    assert_eq!(load_color(99), fg_color!(br_green));
+    assert_eq!(load_color(400), fg_color!(br_yellow));
+    assert_eq!(load_color(401), fg_color!(br_red));
+}
+
+#[test]
+fn test_handle_cqe() {
+    let args = args::Args::default();
+    let mut view = View {
+        position: Some((5, 5)),
+        ..Default::default()
+    };
+    let mut stopwatch = None;
+    let mut input_buf = [0; 32];
+    input_buf[..4].copy_from_slice(b"\x1b[Aq");
+    let ring = IoUring::new(1).unwrap();
+    let input = IoUringReadHandle::new(&ring, io::STDIN as _, &mut input_buf, Token::Read as _);
+    let load = Cell::new(None);
+    let mut frame = [0; 64 * 1024];
+    let mut writer = Output::Fd(BufWriter::new(FdWriter::stdout(), &mut frame));
+    let mut state = State {
+        args: &args,
+        view: &mut view,
+        stopwatch: &mut stopwatch,
+        scale: 1,
+        input: &input,
+        input_pool: &[],
+        siginfo_buf: &(nc::SIGWINCH as u32).to_ne_bytes(),
+        loadavg_buf: b"2.50 1.00 0.50 1/100 42\n",
+        load: &load,
+        writer: &mut writer,
+    };
+    let cqe = |token: Token, res| nc::io_uring_cqe_t {
+        user_data: token as _,
+        res,
+        flags: 0,
+    };
+    let mut handle = |token, res| handle_cqe(&cqe(token, res), &mut state);
+
+    let timeout = Action {
+        timeout: true,
+        ..Default::default()
+    };
+    assert_eq!(handle(Token::Timeout, -nc::ETIME), Ok(timeout));
+    assert_eq!(
+        handle(Token::Timeout, -nc::ECANCELED),
+        Ok(Action::default())
+    );
+    let read = Action {
+        changed: true,
+        quit: true,
+        input: true,
+        rearm: true,
+        ..Default::default()
+    };
+    assert_eq!(handle(Token::Read, 4), Ok(read));
+    let eof = Action {
+        quit: true,
+        rearm: true,
+        ..Default::default()
+    };
+    assert_eq!(handle(Token::Read, 0), Ok(eof));
+    assert_eq!(handle(Token::Loadavg, 24), Ok(Action::default()));
+    assert_eq!(handle(Token::Signal, -nc::EBADF), Err(nc::EBADF));
+    let winch = Action {
+        winch: true,
+        ..Default::default()
+    };
+    assert_eq!(handle(Token::Signal, 128), Ok(winch));
+    let term = (nc::SIGTERM as u32).to_ne_bytes();
+    state.siginfo_buf = &term;
+    assert_eq!(
+        handle_cqe(&cqe(Token::Signal, 128), &mut state),
+        Ok(Action {
+            quit: true,
+            ..Default::default()
+        })
+    );
+    assert_eq!(view.position, Some((5, 4)));
+    assert_eq!(load.get(), Some(250));
+}
+
+#[test]
+fn test_for_each_key() {
+    let mut keys = [const { None }; 8];
+    let mut n = 0;
+    for_each_key(
+        b"q\x1b[A\x1b[1;5C\x1b[M !+\x1b[Ds\x1b[8;24;80t\x1b[8;;80t\x1b",
+        |key| {
+            keys[n] = Some(key);
+            n += 1;
+        },
+    );
+    assert_eq!(
+        keys,
+        [
+            Some(Key::Byte(b'q')),
+            Some(Key::Arrow(Direction::Up)),
+            Some(Key::Mouse(0, 1, 11)),
+            Some(Key::Arrow(Direction::Left)),
+            Some(Key::Byte(b's')),
+            Some(Key::Size(24, 80)),
+            Some(Key::Esc),
+            None,
+        ]
+    );
+}
+
+#[test]
+fn test_shift_arrows() {
+    let mut keys = [const { None }; 6];
+    let mut n = 0;
+    for_each_key(b"\x1b[1;2A\x1b[1;2B\x1b[1;2C\x1b[1;2D\x1b", |key| {
+        keys[n] = Some(key);
+        n += 1;
+    });
+    assert_eq!(
+        keys,
+        [
+            Some(Key::ShiftArrow(Direction::Up)),
+            Some(Key::ShiftArrow(Direction::Down)),
+            Some(Key::ShiftArrow(Direction::Right)),
+            Some(Key::ShiftArrow(Direction::Left)),
+            Some(Key::Esc),
+            None,
+        ]
+    );
+    let step = |offset, direction| cycle_tz_offset(offset, arrow_tz_step(direction));
+    assert_eq!(step(0, Direction::Right), 1800);
+    assert_eq!(step(5 * 3600, Direction::Left), 4 * 3600 + 1800);
+    assert_eq!(step(14 * 3600, Direction::Up), -12 * 3600);
+    assert_eq!(step(-12 * 3600, Direction::Down), 14 * 3600);
+    assert_eq!(step(14 * 3600, Direction::Right), -12 * 3600);
+    assert_eq!(step(13 * 3600 + 1800, Direction::Up), -12 * 3600);
+    assert_eq!(step(-12 * 3600, Direction::Left), 14 * 3600);
+    // anything the parsers accept stays in range
+    let (min, max) = (args::MIN_TZ_OFFSET, args::MAX_TZ_OFFSET);
+    assert_eq!(step(min + 1800, Direction::Left), min);
+    assert_eq!(step(max - 1800, Direction::Right), max);
+    for direction in [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ] {
+        for offset in [min, max] {
+            assert!((min..=max).contains(&step(offset, direction)));
+        }
+    }
+}
+
+#[test]
+fn test_click_step() {
+    assert_eq!(click_step(1, 10, 38), -3600);
+    assert_eq!(click_step(11, 10, 38), 0);
+    assert_eq!(click_step(48, 10, 38), 0);
+    assert_eq!(click_step(49, 10, 38), 3600);
+    assert_eq!(cycle_tz_offset(14 * 3600, 3600), -12 * 3600);
+    assert_eq!(cycle_tz_offset(-12 * 3600, -3600), 14 * 3600);
+    assert_eq!(cycle_tz_offset(5 * 3600 + 1800, -3600), 4 * 3600 + 1800);
+}
+
+#[test]
+fn test_cursor_position_to() {
+    let mut buf = [0; 16];
+    let mut writer = ArrayWriter::new(&mut buf);
+    cursor_position_to(&mut writer, 12, 345).unwrap();
+    let len = writer.len;
+    assert_eq!(buf[..len], *b"\x1b[12;345H");
+    assert_eq!(cursor_position_to!(3, 40), b"\x1b[3;40H");
+}
+
+/// Reads what stdin has into `buf`; `None` if nothing comes within `timeout`.
+fn read_with_timeout(
+    ring: &IoUring,
+    buf: &mut [u8],
+    timeout: &nc::timespec_t,
+) -> io::Result<Option<usize>> {
+    let (read, link) = (1, nc::IOSQE_IO_LINK as _);
+    ring.prepare(
+        io_uring::OpCode::IORING_OP_READ,
+        io::STDIN as _,
+        buf.as_mut_ptr() as _,
+        buf.len(),
+        read,
+        0,
+        link,
+    )?;
+    ring.prepare_linked_timeout(timeout, 2)?;
+    ring.submit_wait(2)?;
+    let (mut len, mut pending) = (None, 2);
+    while pending != 0 {
+        let Some(cqe) = ring.complete() else {
+            ring.wait()?;
+            continue;
+        };
+        pending -= 1;
+        match (cqe.user_data, cqe.result()) {
+            (1, Ok(n)) => len = Some(n as usize),
+            (1, Err(nc::ECANCELED)) | (2, _) => {}
+            (_, Err(e)) => return Err(e),
+            _ => return Err(nc::EIO),
+        }
+    }
+    Ok(len)
+}
+
+/// Asks the terminal for a `ESC[row;colR` report. `None` if no answer comes within 100 ms; keys
+/// typed around it are kept in `typed` for [`handle_input`].
+fn query_cursor_pos(ring: &IoUring, typed: &mut ArrayWriter<32>) -> io::Result<Option<(u16, u16)>> {
+    const TIMEOUT: nc::timespec_t = timespec(100_000_000);
+    ring.prepare_write(io::STDOUT as _, b"[6n", 0)?;
+    ring.submit_wait(1)?;
+    ring.complete().ok_or(nc::EIO)?.result()?;
+    let mut buf = [0; 32];
+    let mut len = 0;
+    while len < buf.len() {
+        match read_with_timeout(ring, &mut buf[len..], &TIMEOUT)? {
+            None | Some(0) => break,
+            Some(n) => len += n,
+        }
+        if let Some((start, end, pos)) = find_cursor_pos(&buf[..len]) {
+            typed.write(&buf[..start])?;
+            typed.write(&buf[end..len])?;
+            return Ok(Some(pos));
+        }
+    }
+    typed.write(&buf[..len])?;
+    Ok(None)
+}
+
+/// Asks the terminal for its size with `CSI 18t`. `None` if no answer comes within 100 ms.
+fn query_window_size(ring: &IoUring) -> io::Result<Option<(u16, u16)>> {
+    const TIMEOUT: nc::timespec_t = timespec(100_000_000);
+    ring.prepare_write(io::STDOUT as _, buffer_size!(), 0)?;
+    ring.submit_wait(1)?;
+    ring.complete().ok_or(nc::EIO)?.result()?;
+    let mut buf = [0; 32];
+    let Some(len) = read_with_timeout(ring, &mut buf, &TIMEOUT)? else {
+        return Ok(None);
+    };
+    let start = buf[..len].windows(2).position(|w| w == b"[");
+    Ok(start.and_then(|start| parse_size_report(&buf[start + 2..len])))
+}
+
+/// `8;rows;colst`, a `CSI 18t` answer after its `ESC[`.
+fn parse_size_report(report: &[u8]) -> Option<(u16, u16)> {
+    let report = report.strip_prefix(b"8;")?;
+    let report = &report[..report.iter().position(|&c| c == b't')?];
+    let semicolon = report.iter().position(|&c| c == b';')?;
+    let rows = args::parse_u64(&report[..semicolon])?;
+    let cols = args::parse_u64(&report[semicolon + 1..])?;
+    match (rows.try_into().ok()?, cols.try_into().ok()?) {
+        (0, _) | (_, 0) => None,
+        size => Some(size),
+    }
+}
+
+/// The first `ESC[row;colR` in `input`, with where it starts and ends.
+fn find_cursor_pos(input: &[u8]) -> Option<(usize, usize, (u16, u16))> {
+    (0..input.len()).find_map(|start| {
+        let report = input[start..].strip_prefix(b"[")?;
+        let len = report.iter().position(|&c| c == b'R')?;
+        let report = &report[..len];
+        let semicolon = report.iter().position(|&c| c == b';')?;
+        let row = args::parse_u64(&report[..semicolon])?;
+        let col = args::parse_u64(&report[semicolon + 1..])?;
+        let pos = (row.try_into().ok()?, col.try_into().ok()?);
+        Some((start, start + len + 3, pos))
+    })
+}
+
+/// Columns taken by `▓▒░`: 3 if the terminal renders block elements, 6 if it
+/// draws them double-width. `None` if the terminal doesn't say where the cursor went.
+fn block_glyph_width(ring: &IoUring, typed: &mut ArrayWriter<32>) -> io::Result<Option<u16>> {
+    FdWriter::stdout().write_all("\r▓▒░".as_bytes())?;
+    let pos = query_cursor_pos(ring, typed)?;
+    FdWriter::stdout().write_all(b"\r[K")?;
+    Ok(pos.map(|(_, col)| col.saturating_sub(1)))
+}
+
+#[test]
+fn test_parse_cursor_pos() {
+    assert_eq!(find_cursor_pos(b"\x1b[12;34R"), Some((0, 8, (12, 34))));
+    assert_eq!(find_cursor_pos(b"q\x1b[1;4Rj"), Some((1, 7, (1, 4))));
+    assert_eq!(find_cursor_pos(b"\x1b[A\x1b[2;5R"), Some((3, 9, (2, 5))));
+    assert_eq!(find_cursor_pos(b"\x1b[12;3"), None);
+    assert_eq!(find_cursor_pos(b"\x1b[;3R"), None);
+    assert_eq!(parse_size_report(b"8;24;80t"), Some((24, 80)));
+    assert_eq!(parse_size_report(b"8;0;80t"), None);
+    assert_eq!(parse_size_report(b"4;600;800t"), None);
+}
+
+enum Output<'a> {
+    Fd(BufWriter<&'a mut [u8; 64 * 1024], FdWriter>),
+    Uring(UringWriter<'a>),
+}
+
+impl Output<'_> {
+    fn complete(&mut self, cqe: &nc::io_uring_cqe_t) -> io::Result<()> {
+        match self {
+            Output::Fd(_) => Err(nc::EIO),
+            Output::Uring(writer) => writer.complete(cqe),
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        match self {
+            Output::Fd(_) => true,
+            Output::Uring(writer) => writer.is_idle(),
+        }
+    }
+}
+
+impl io::Write for Output<'_> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Fd(writer) => writer.write(bytes),
+            Output::Uring(writer) => writer.write(bytes),
+        }
+    }
+    fn flush(&mut self) -> io::Result<usize> {
+        match self {
+            Output::Fd(writer) => writer.flush(),
+            Output::Uring(writer) => writer.flush(),
+        }
+    }
+    fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match self {
+            Output::Fd(writer) => writer.write_all(bytes),
+            Output::Uring(writer) => writer.write_all(bytes),
+        }
+    }
+}
+
+impl core::fmt::Write for Output<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        io::Write::write_all(self, s.as_bytes()).map_err(|_| core::fmt::Error)
+    }
+}
+
+fn main(args: ArgIter, env: ArgIter) -> io::Result<()> {
+    if let Some(b"--version") = args.clone().nth(1) {
+        FdWriter::stdout().write_all(VERSION)?;
+        exit(0);
+    }
+    if let Some(b"-h" | b"--help") = args.clone().nth(1) {
+        FdWriter::stdout().write_all(HELP)?;
+        exit(0);
+    }
+    if let Some(b"--features") = args.clone().nth(1) {
+        print_features()?;
+        exit(0);
+    }
+    // the parser has already said what was wrong
+    let mut args = args::parse_args(args, env).unwrap_or_else(|e| exit(e as _));
+    // not time(2), which arm64 doesn't have
+    let get_time = || -> io::Result<isize> { Ok(realtime()?.tv_sec as _) };
+    let elapsed = |now: isize| (now as u64).saturating_sub(args.epoch.unwrap_or_default());
+    if args.plain {
+        let mut stdout = FdWriter::stdout();
+        match args.iso8601 {
+            _ if args.epoch.is_some() => {
+                stdout.write_all(&draw::draw_elapsed(elapsed(get_time()?)))?
+            }
+            true => stdout.write_all(&draw::draw_time_iso8601(get_time()? as _, args.tz_offset))?,
+            false => stdout.write_all(&draw::draw_time_minimal(get_time()?, args.tz_offset))?,
+        }
+        if args.newline {
+            stdout.write_all(b"\n")?;
+        }
+        return Ok(());
+    }
+    if args.once || !is_tty(io::STDIN) || !is_tty(io::STDOUT) {
+        if args.epoch.is_some() {
+            let mut stdout = FdWriter::stdout();
+            stdout.write_all(&draw::draw_elapsed(elapsed(get_time()?)))?;
+            return stdout.write_all(b"\n");
+        }
+        return print_once(
+            &mut FdWriter::stdout(),
+            get_time()? + args.tz_offset as isize,
+        );
+    }
+    unsafe { TZ_OFFSET = args.tz_offset };
+    unsafe { TITLE = args.title };
+    ALTERNATE_SCREEN.store(args.alternate_screen, Ordering::Relaxed);
+    if let Some(fd) = args.error_fd {
+        io::set_error_fd(fd);
+    }
+    io::set_sync_errors(args.sync_log);
+
+    let ring = match args.sqpoll {
+        Some(idle_ms) => IoUring::builder().sqpoll(idle_ms).build(16)?,
+        None => IoUring::new(16)?,
+    };
+
+    #[allow(static_mut_refs)]
+    unsafe {
+        nc::ioctl(io::STDIN, nc::TCGETS, TERMIOS.as_ptr() as _)?;
+        TERMIOS_SAVED.store(true, Ordering::Release);
+        let mut termios = TERMIOS.assume_init_ref().clone();
+        termios.c_lflag &= !(nc::ECHO | nc::ICANON);
+        nc::ioctl(io::STDIN, nc::TCSETS, &raw const termios as _)?;
+        WINSIZE.write(nc::winsize_t::default());
+    }
+    if !read_winsize()
+        && let Some((rows, cols)) = query_window_size(&ring)?
+    {
+        set_winsize(rows, cols);
+    }
+
+    let mut typed_buf = [0; 32];
+    let mut typed = ArrayWriter::new(&mut typed_buf);
+    if args.unicode_check
+        && !matches!(args.style, Style::Ascii)
+        && block_glyph_width(&ring, &mut typed)?.is_some_and(|width| width != 3)
+    {
+        args.style = Style::Braun;
+    }
+    let typed_len = typed.len;
+    // keys that came in while the terminal was asked something, handled before the first wait
+    let mut typed = Some(&typed_buf[..typed_len]).filter(|keys| !keys.is_empty());
+
+    // room for a whole frame at `args::MAX_SCALE`
+    let mut buf = MaybeUninit::<[[u8; 64 * 1024]; 2]>::uninit();
+    let [front, back] = unsafe { buf.assume_init_mut() };
+    let mut ctx = draw::Context::new(if args.uring_write {
+        Output::Uring(UringWriter::new(
+            &ring,
+            io::STDOUT,
+            Token::Write as _,
+            [front, back],
+        ))
+    } else {
+        Output::Fd(BufWriter::new(FdWriter::stdout(), front))
+    });
+
+    let seconds = Cell::new(get_time()?);
+    let mut stopwatch = match args.mode {
+        ClockMode::Wall => None,
+        ClockMode::Timer | ClockMode::Stopwatch => Some(Stopwatch::new(monotonic()?)),
+    };
+    let centiseconds = Cell::new(args.timer * 100);
+    let expired = Cell::new(false);
+    let pomodoro = Cell::new(args.pomodoro.map(Pomodoro::new));
+    let paused = Cell::new(false);
+    // counts down while a chime or alarm flashes the digits
+    let flash = Cell::new(0u8);
+    let load = Cell::new(None);
+
+    let drawn_alert = Cell::new(None);
+    let drawn_title = Cell::new(None);
+
+    let fps = args.fps.unwrap_or(match args.mode {
+        ClockMode::Wall => 1,
+        ClockMode::Timer | ClockMode::Stopwatch => 10,
+    });
+    let config = Config {
+        format: args.format,
+        blink_colon: args.blink,
+        show_seconds: !args.hide_seconds,
+        show_date: args.date,
+        fg_color: args.color.unwrap_or(Config::default().fg_color),
+        bg_color: args.bg_color,
+        colors: args.colors,
+    };
+    // Without `full`, only the digits that changed since the last frame are drawn.
+    let redraw = |ctx: &mut draw::Context<Output>, view: &View, full: bool| -> io::Result<()> {
+        let colors = config.colors;
+        let resting = pomodoro.get().is_some_and(|pomodoro| pomodoro.resting);
+        let alert = match (expired.get(), flash.get() % 2) {
+            _ if colors.no_color => None,
+            (true, _) => Some(&fg_color!(br_red)[..]),
+            (false, 1) => Some(&fg_color!(br_white)[..]),
+            (false, _) if resting => Some(&fg_color!(br_green)[..]),
+            (false, _) => load.get().map(load_color),
+        };
+        // tiles and zones share the one frame the digits are diffed against
+        let full = full
+            || view.rainbow
+            || args.grid.is_some()
+            || args.zones.len > 1
+            || args.shadow
+            || drawn_alert.replace(alert) != alert;
+        let primary = match (alert, view.color()) {
+            (Some(color), _) | (None, Some(color)) => Ink::Sgr(color),
+            (None, None) => Ink::Color(config.fg_color),
+        };
+        // dim leaves the color alone, so undoing it is enough
+        let inks = args.seconds_ink.and_then(|ink| match ink {
+            Ink::Sgr(_) => Some((Ink::Sgr(normal_intensity!()), ink)),
+            Ink::Color(_) => Some((colors.ink(primary)?, colors.ink(ink)?)),
+        });
+        if full {
+            ctx.invalidate();
+            // set before clearing, so the clear fills the screen with it
+            if let Some(bg) = config.bg_color.and_then(|bg| colors.color(bg)) {
+                let mut buf = [0; draw::COLOR_SEQUENCE_SISE];
+                let len = bg.ansi_sequence_bg(&mut buf);
+                ctx.writer.write_all(&buf[..len])?;
+            }
+            // clears in place: leaving the alternate screen to get a fresh one blinks
+            ctx.writer
+                .write_all(concat_bytes!(clear_screen!(), cursor_position!()))?;
+            if let Some(primary) = colors.ink(primary) {
+                color::write_ink(&mut ctx.writer, primary)?;
+            }
+        }
+        let (ws_row, ws_col) = winsize();
+        if !fits(face_size(&args, view), (ws_row, ws_col)) {
+            // the frame starts out cleared, so the message only needs writing once
+            if full {
+                cursor_position_to(&mut ctx.writer, ws_row as u64 / 2 + 1, 1)?;
+                draw::write_centered_line(&mut ctx.writer, b"Terminal too small", ws_col as _)?;
+            }
+            return ctx.writer.flush().map(drop);
+        }
+        if args.mode != ClockMode::Wall {
+            let (left, top) = margins();
+            let position = (top as u64 + 1, left as u64 + 1);
+            let centiseconds = centiseconds.get();
+            draw_stopwatch(ctx, view, position, centiseconds, config.colors.truecolor)?;
+            if let Some(pomodoro) = pomodoro.get() {
+                let mut buf = [0; 32];
+                let mut label = ArrayWriter::new(&mut buf);
+                pomodoro.write_label(&mut label, paused.get())?;
+                let len = label.len;
+                let width = view.style.stopwatch_size().0 as usize * ctx.scale();
+                ctx.writer.write_all(cursor_position!())?;
+                ctx.writer.write_all(margin_bottom())?;
+                ctx.writer.write_all(margin_left())?;
+                ctx.writer.write_all(dim!())?;
+                draw::write_centered_line(&mut ctx.writer, &buf[..len], width as _)?;
+                ctx.writer.write_all(normal_intensity!())?;
+            }
+        } else if let Some(grid) = &args.grid {
+            draw_grid(
+                ctx,
+                view,
+                grid,
+                seconds.get(),
+                config.colors.truecolor,
+                inks,
+            )?;
+        } else if args.zones.len > 0 {
+            let layout = Layout::new(&args, view).fit(ws_row);
+            let (left, top) = margins();
+            let scale = ctx.scale() as u16;
+            for i in 0..layout.faces as usize {
+                let (down, right) = layout.offset(i as _, scale);
+                let (row, col) = ((top + down) as u64 + 1, (left + right) as u64 + 1);
+                if full && layout.label_rows != 0 {
+                    let width = layout.face.0 * scale;
+                    cursor_position_to(&mut ctx.writer, row, col)?;
+                    draw::write_centered_line(&mut ctx.writer, args.zones.labels[i], width as _)?;
+                }
+                let position = (row + layout.label_rows as u64, col);
+                let seconds = seconds.get() + args.zones.offsets[i] as isize;
+                draw_clock(ctx, view, position, seconds, config.colors.truecolor, inks)?;
+            }
+        } else if args.epoch.is_some() {
+            let (left, top) = margins();
+            let position = (top as u64 + 1, left as u64 + 1);
+            let elapsed = elapsed(seconds.get());
+            let view = View {
+                format: ClockFormat::TwentyFourHour,
+                ..*view
+            };
+            draw_clock(
+                ctx,
+                &view,
+                position,
+                (elapsed % (24 * 3600)) as _,
+                config.colors.truecolor,
+                inks,
+            )?;
+            // the days go under the face, small and dim, rather than widening it
+            if elapsed >= 24 * 3600 {
+                let width = view.style.size(view.seconds).0 as usize * ctx.scale();
+                ctx.writer.write_all(cursor_position!())?;
+                ctx.writer.write_all(margin_bottom())?;
+                ctx.writer.write_all(margin_left())?;
+                ctx.writer.write_all(dim!())?;
+                draw::write_centered_line(
+                    &mut ctx.writer,
+                    &draw::draw_elapsed(elapsed),
+                    width as _,
+                )?;
+                ctx.writer.write_all(normal_intensity!())?;
+            }
+        } else {
+            let (left, top) = margins();
+            let position = (top as u64 + 1, left as u64 + 1);
+            let seconds = seconds.get() + tz_offset() as isize;
+            match view.style {
+                Style::Block if args.shadow => {
+                    let label = view.format.label(seconds);
+                    let fg = colors.ink(primary);
+                    draw::draw_time_with_shadow(ctx, position, seconds, view.config(), fg, label)?;
+                }
+                _ => draw_clock(ctx, view, position, seconds, config.colors.truecolor, inks)?,
+            }
+            if let Some(label) = args.label
+                && view.style == Style::Block
+            {
+                draw::draw_label_under(ctx, position, view.config(), args.shadow, label)?;
+            }
+            if config.show_date {
+                let width = view.style.size(view.seconds).0 as usize * ctx.scale();
+                ctx.writer.write_all(cursor_position!())?;
+                ctx.writer.write_all(margin_bottom())?;
+                ctx.writer.write_all(margin_left())?;
+                ctx.writer.write_all(dim!())?;
+                draw::write_centered_line(&mut ctx.writer, &draw::draw_date(seconds), width as _)?;
+                ctx.writer.write_all(normal_intensity!())?;
+            }
+        }
+        let now = seconds.get() + tz_offset() as isize;
+        let title = Some((now.div_euclid(60), view.format));
+        if args.title && drawn_title.replace(title) != title {
+            let mut buf = [0; 16];
+            let len = write_title(&mut buf, now, view.format)?;
+            ctx.writer.write_all(&buf[..len])?;
+        }
+        if args.status_bar && full {
+            let (ws_row, ws_col) = winsize();
+            cursor_position_to(&mut ctx.writer, ws_row as _, 1)?;
+            draw::draw_status_bar(&mut ctx.writer, args.mode, fps, tz_offset(), ws_col)?;
+        }
+        ctx.writer.flush()?;
+        Ok(())
+    };
+
+    let mut view = View {
+        seconds: config.show_seconds,
+        rainbow: args.rainbow && !args.colors.no_color,
+        blink: config.blink_colon,
+        format: config.format,
+        style: args.style,
+        ..Default::default()
+    };
+    let signal_fd = block_signals()?;
+    // tiles in a grid stay at scale 1
+    let max_scale = if args.grid.is_some() { 1 } else { args.scale };
+    setup(&mut FdWriter::stdout(), args.mouse)?;
+    ctx.set_scale(resize(face_size(&args, &view), &mut view, max_scale)? as _);
+    redraw(&mut ctx, &view, true)?;
+
+    // one worker is plenty for the blocking reads, and kernels before 5.15 don't know the call
+    _ = ring.register_iowq_max_workers(1, 0);
+
+    let mut input_buf = MaybeUninit::<[u8; 32]>::uninit();
+    let input_iovec = nc::iovec_t {
+        iov_base: input_buf.as_ptr() as _,
+        iov_len: size_of_val(&input_buf),
+    };
+    let fixed = match ring
+        .register_files(&[io::STDIN])
+        .and_then(|_| ring.register_buffers(&[input_iovec]))
+    {
+        Ok(_) => true,
+        // plain reads do the same, whatever kept the registration from working
+        Err(_) => {
+            _ = ring.unregister_files();
+            false
+        }
+    };
+    let input_buf = unsafe { input_buf.assume_init_mut() };
+    let mut stdin_read = IoUringReadHandle::new(&ring, io::STDIN as _, input_buf, Token::Read as _);
+    const INPUT_GROUP: u16 = 0;
+    let mut input_pool = MaybeUninit::<[[u8; 32]; 4]>::uninit();
+    let multishot = ring.probe(IORING_OP_READ_MULTISHOT);
+    // so a read can't outlive the loop on a stdin that never delivers anything
+    let read_timeout = nc::timespec_t {
+        tv_sec: 5,
+        tv_nsec: 0,
+    };
+    let read_stdin = |input: &mut IoUringReadHandle| {
+        let (read, link) = (Token::Read as _, nc::IOSQE_IO_LINK as _);
+        match (multishot, fixed) {
+            (true, _) => ring.prepare_read_multishot(io::STDIN as _, INPUT_GROUP, read, link),
+            (false, true) => input.arm_fixed(0, 0, link),
+            (false, false) => input.arm(link),
+        }?;
+        ring.prepare_linked_timeout(&read_timeout, Token::ReadTimeout as _)
+    };
+    // struct signalfd_siginfo
+    let mut siginfo_buf = MaybeUninit::<[u8; 128]>::uninit();
+    // ticks `fps` times a second while the seconds show, otherwise once at each new minute,
+    // each one armed for an absolute time so they don't drift off the second boundaries
+    let tick = 1_000_000_000 / fps as i64;
+    let mut deadline = nc::timespec_t::default();
+    let mut arm_timeout = |ticking: bool| -> io::Result<()> {
+        let interval = if ticking { tick } else { 60 * 1_000_000_000 };
+        deadline = timespec(next_tick(nanos(&realtime()?), interval));
+        let flags = nc::IORING_TIMEOUT_ABS | io_uring::IORING_TIMEOUT_REALTIME;
+        ring.prepare_timeout(&deadline, Token::Timeout as _, flags)?;
+        Ok(())
+    };
+    let mut ticking = view.seconds || args.mode != ClockMode::Wall || args.screensaver;
+    // when the chime and alarm were last looked for
+    let mut checked = seconds.get();
+    let (mut saver, mut last_input, mut drift_at) = (None, seconds.get(), 0);
+    let mut clocks = (nanos(&realtime()?), nanos(&monotonic()?));
+    let loadavg = match args.color_by_load {
+        true => Some(open_loadavg()?),
+        false => None,
+    };
+    let mut loadavg_buf = MaybeUninit::<[u8; 64]>::uninit();
+    let read_loadavg =
+        |fd: i32, buf: &mut [u8]| ring.prepare_read(fd as _, buf, Token::Loadavg as _);
+    ring.submit_batch(|ring| {
+        if multishot {
+            let pool = unsafe { input_pool.assume_init_mut() };
+            ring.prepare_provide_buffers(pool, INPUT_GROUP, 0, Token::Provide as _)?;
+        }
+        read_stdin(&mut stdin_read)?;
+        ring.prepare_read(
+            signal_fd as _,
+            unsafe { siginfo_buf.assume_init_mut() },
+            Token::Signal as _,
+        )?;
+        arm_timeout(ticking)?;
+        if let Some(fd) = loadavg {
+            read_loadavg(fd, unsafe { loadavg_buf.assume_init_mut() })?;
+        }
+        Ok(())
+    })?;
+
+    fn wait(ring: &IoUring) -> io::Result<()> {
+        loop {
+            match ring.wait() {
+                Ok(_) => break Ok(()),
+                Err(x) if x == nc::EINTR => continue,
+                Err(x) => break Err(x),
+            }
+        }
+    }
+
+    let mut run = || -> io::Result<()> {
+        loop {
+            if ring.cq_overflow() {
+                ring.flush_overflow()?;
+            }
+            if typed.is_none() {
+                wait(&ring)?;
+            }
+            let (mut action, mut error) = (Action::default(), Ok(()));
+            let mut state = State {
+                args: &args,
+                view: &mut view,
+                stopwatch: &mut stopwatch,
+                scale: ctx.scale() as _,
+                input: &stdin_read,
+                input_pool: unsafe { input_pool.assume_init_ref() },
+                siginfo_buf: unsafe { siginfo_buf.assume_init_ref() },
+                loadavg_buf: unsafe { loadavg_buf.assume_init_ref() },
+                load: &load,
+                writer: &mut ctx.writer,
+            };
+            if let Some(keys) = typed.take() {
+                action.merge(handle_input(keys, &mut state)?);
+            }
+            ring.for_each_completion(|cqe| match handle_cqe(cqe, &mut state) {
+                Ok(other) => action.merge(other),
+                Err(e) => error = Err(e),
+            })?;
+            error?;
+            let Action {
+                timeout,
+                winch,
+                changed,
+                flashed,
+                quit,
+                input,
+                rearm,
+                returned,
+            } = action;
+
+            ring.submit_batch(|ring| {
+                // the answer comes back on stdin as a `Key::Size`; until then the old size stands
+                if winch && !read_winsize() {
+                    ctx.writer.write_all(buffer_size!())?;
+                    ctx.writer.flush()?;
+                }
+                // the screen may have been left stale by whatever ran while the clock slept
+                let mut stepped = false;
+                if timeout {
+                    let now = (nanos(&realtime()?), nanos(&monotonic()?));
+                    let (real, mono) = mem::replace(&mut clocks, now);
+                    stepped = clock_stepped(now.0 - real, now.1 - mono);
+                }
+                // any key brings a drifting clock back to the middle
+                let mut moved = false;
+                if input {
+                    last_input = get_time()?;
+                    if saver.take().is_some() {
+                        (view.position, moved) = (None, true);
+                    }
+                }
+                if timeout && args.screensaver {
+                    let now = get_time()?;
+                    if now - last_input >= args.idle_secs as isize && now >= drift_at {
+                        let saver = saver.get_or_insert_with(|| ScreensaverState::new(margins()));
+                        let (width, height) = face_size(&args, &view);
+                        let scale = ctx.scale() as u16;
+                        #[allow(static_mut_refs)]
+                        let nc::winsize_t { ws_row, ws_col, .. } =
+                            unsafe { WINSIZE.assume_init_ref() };
+                        saver.update(*ws_row, *ws_col, height * scale, width * scale);
+                        view.position = Some(saver.pos);
+                        (drift_at, moved) = (now + 5, true);
+                    }
+                }
+                if winch || changed || moved {
+                    ctx.set_scale(resize(face_size(&args, &view), &mut view, max_scale)? as _);
+                }
+                if winch {
+                    ring.prepare_read(
+                        signal_fd as _,
+                        unsafe { siginfo_buf.assume_init_mut() },
+                        Token::Signal as _,
+                    )?;
+                }
+                // a fired timeout is gone, one still pending goes before re-arming
+                if (view.seconds || args.mode != ClockMode::Wall || args.screensaver) != ticking
+                    || timeout
+                {
+                    if !timeout {
+                        ring.prepare_timeout_remove(
+                            Token::Timeout as _,
+                            Token::TimeoutRemove as _,
+                        )?;
+                    }
+                    ticking = view.seconds || args.mode != ClockMode::Wall || args.screensaver;
+                    arm_timeout(ticking)?;
+                }
+                if timeout && args.mode == ClockMode::Wall && (args.chime || args.alarm.is_some()) {
+                    let now = get_time()?;
+                    let (prev, now) = (mem::replace(&mut checked, now), now);
+                    let (prev, now) = (prev + tz_offset() as isize, now + tz_offset() as isize);
+                    let alarm = |at: u32| crossed(prev, now, at as _, 24 * 3600);
+                    if args.chime && crossed(prev, now, 0, 3600) || args.alarm.is_some_and(alarm) {
+                        for _ in 0..args.bell_repeat {
+                            ctx.writer.write_all(bell!())?;
+                        }
+                        ctx.writer.flush()?;
+                        if flash.replace(FLASHES) == 0 {
+                            ring.prepare_timeout(&FLASH_INTERVAL, Token::Flash as _, 0)?;
+                        }
+                    }
+                }
+                if flashed {
+                    flash.set(flash.get().saturating_sub(1));
+                    if flash.get() != 0 {
+                        ring.prepare_timeout(&FLASH_INTERVAL, Token::Flash as _, 0)?;
+                    }
+                }
+                // a paused clock skips ticks but shows the right time again once resumed
+                let timeout = timeout && !view.paused;
+                if timeout || changed && !view.paused {
+                    seconds.set(get_time()?);
+                }
+                if let Some(stopwatch) = stopwatch.as_mut().filter(|_| timeout || changed) {
+                    let now = monotonic()?;
+                    let elapsed = stopwatch.elapsed_centiseconds(&now);
+                    let total = pomodoro
+                        .get()
+                        .map_or(args.timer * 100, |pomodoro| pomodoro.total());
+                    let (mut shown, ran_out) = match args.mode {
+                        ClockMode::Timer => countdown(total, elapsed, args.overrun),
+                        _ => (elapsed, false),
+                    };
+                    // a finished phase rings and flashes like the alarm, then waits for Space
+                    if let Some(mut next) = pomodoro.get().filter(|_| ran_out) {
+                        next.advance();
+                        pomodoro.set(Some(next));
+                        *stopwatch = Stopwatch {
+                            paused_at: Some(now.clone()),
+                            ..Stopwatch::new(now)
+                        };
+                        shown = next.total();
+                        for _ in 0..args.bell_repeat {
+                            ctx.writer.write_all(bell!())?;
+                        }
+                        ctx.writer.flush()?;
+                        if flash.replace(FLASHES) == 0 {
+                            ring.prepare_timeout(&FLASH_INTERVAL, Token::Flash as _, 0)?;
+                        }
+                    } else if !expired.replace(ran_out) && ran_out {
+                        for _ in 0..args.bell_repeat {
+                            ctx.writer.write_all(bell!())?;
+                        }
+                        ctx.writer.flush()?;
+                    }
+                    paused.set(stopwatch.paused_at.is_some());
+                    centiseconds.set(shown);
+                }
+                if timeout || winch || changed || flashed || moved {
+                    redraw(&mut ctx, &view, winch || changed || moved || stepped)?;
+                }
+                if let Some(fd) = loadavg.filter(|_| timeout) {
+                    read_loadavg(fd, unsafe { loadavg_buf.assume_init_mut() })?;
+                }
+                if quit {
+                    return Ok(());
+                }
+                let pool = unsafe { input_pool.assume_init_mut() };
+                for id in (0..pool.len()).filter(|id| returned & 1 << id != 0) {
+                    ring.prepare_provide_buffers(
+                        &mut pool[id..id + 1],
+                        INPUT_GROUP,
+                        id as _,
+                        Token::Provide as _,
+                    )?;
+                }
+                if rearm {
+                    read_stdin(&mut stdin_read)?;
+                }
+                Ok(())
+            })?;
+            if quit {
+                return Ok(());
+            }
+        }
+    };
+    let result = run();
+    // let the last frame land before the terminal is restored
+    while result.is_ok() && !ctx.writer.is_idle() {
+        wait(&ring)?;
+        ring.for_each_completion(|cqe| {
+            if cqe.user_data == Token::Write as _ {
+                _ = ctx.writer.complete(cqe);
+            }
+        })?;
+    }
+    on_exit()?;
+    result
+}
+
+#[cfg(target_arch = "x86_64")]
+#[cfg_attr(not(test), unsafe(no_mangle))]
+#[unsafe(naked)]
+extern "C" fn _start() -> ! {
+    // rsp points at argc, followed by the argv pointers, a null, the envp pointers and a null
+    naked_asm!("mov rdi, rsp", "and rsp, -16", "call {}", sym start)
+}
+
+#[cfg(target_arch = "aarch64")]
+#[cfg_attr(not(test), unsafe(no_mangle))]
+#[unsafe(naked)]
+extern "C" fn _start() -> ! {
+    // same layout, and sp is already 16-byte aligned at entry
+    naked_asm!("mov x0, sp", "mov x29, xzr", "mov x30, xzr", "bl {}", sym start)
+}
+
+#[cfg_attr(test, allow(unused))]
+extern "C" fn start(sp: *const usize) -> ! {
+    let (args, env) = unsafe { ArgIter::from_stack(sp) };
+    exit(match main(args, env) {
+        Ok(_) => 0,
+        Err(e) => {
+            _ = on_exit();
+            let (name, description) = (io::error_name(e), io::error_description(e));
+            eprint!("clock: error: {} ({})\n", name, description);
+            e as _
+        }
+    });
+}
+
+pub fn exit(status: usize) -> ! {
+    unsafe { nc::exit_group(status as _) };
+}
+
+#[cfg_attr(not(test), panic_handler)]
+pub fn panic(info: &PanicInfo) -> ! {
+    _ = on_exit();
+    let mut writer = FdWriter::error();
+    if let Some(x) = info.location() {
+        _ = io::WriteFmt::write_fmt(&mut writer, format_args!("{}: ", x));
+    }
+    _ = io::WriteFmt::write_fmt(&mut writer, format_args!("{}\n", info.message()));
+    if let Some(mut writer) = io::SyncWriter::error() {
+        _ = writer.flush();
+    }
+    exit(1)
+}
+
+#[cfg_attr(not(test), global_allocator)]
+pub static GLOBAL_ALLOCATOR: GlobalAllocator = GlobalAllocator;
+
+pub struct GlobalAllocator;
+
+unsafe impl GlobalAlloc for GlobalAllocator {
+    unsafe fn alloc(&self, _layout: core::alloc::Layout) -> *mut u8 {
+        null_mut()
+    }
+
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: core::alloc::Layout) {}
+}
+
+// restrict pointers; not references, an empty slice hands in a dangling one
+/// # Safety
+/// `src` must be readable and `dst` writable for `n` bytes, and the two mustn't overlap.
+#[cfg_attr(not(test), unsafe(no_mangle))]
+pub unsafe extern "C" fn memcpy(dst: *mut u8, src: *const u8, mut n: usize) -> *mut u8 {
+    let (start, mut dst, mut src) = (dst, dst, src);
+    // words only when both pointers can be aligned at once
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    if (dst as usize ^ src as usize) & 7 == 0 {
+        while n != 0 && dst as usize & 7 != 0 {
+            unsafe {
+                *dst = *src;
+                dst = dst.add(1);
+                src = src.add(1);
+            }
+            n -= 1;
+        }
+        while n >= 8 {
+            unsafe {
+                *(dst as *mut u64) = *(src as *const u64);
+                dst = dst.add(8);
+                src = src.add(8);
+            }
+            n -= 8;
+        }
+    }
+    while n != 0 {
+        unsafe {
+            *dst = *src;
+            dst = dst.add(1);
+            src = src.add(1);
+        }
+        n -= 1;
+    }
+    start
+}
+
+/// # Safety
+/// `dst` must be writable for `n` bytes.
+#[cfg_attr(not(test), unsafe(no_mangle))]
+pub unsafe extern "C" fn memset(dst: *mut u8, chr: u8, mut n: usize) -> *mut u8 {
+    let (start, mut dst) = (dst, dst);
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    {
+        while n != 0 && dst as usize & 7 != 0 {
+            unsafe {
+                *dst = chr;
+                dst = dst.add(1);
+            }
+            n -= 1;
+        }
+        let word = chr as u64 * 0x0101_0101_0101_0101;
+        while n >= 8 {
+            unsafe {
+                *(dst as *mut u64) = word;
+                dst = dst.add(8);
+            }
+            n -= 8;
+        }
+    }
+    while n != 0 {
+        unsafe {
+            *dst = chr;
+            dst = dst.add(1);
+        }
+        n -= 1;
+    }
+    start
+}
+
+#[test]
+fn test_memcpy_memset() {
+    // xorshift, enough to shake out alignment and length combinations
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut random = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state as usize
+    };
+    let mut src = [0u8; 256];
+    for x in &mut src {
+        *x = random() as _;
+    }
+    for _ in 0..1000 {
+        let (d, s, len) = (random() % 16, random() % 16, random() % 200);
+        let mut dst = [0u8; 256];
+        let ret = unsafe { memcpy(dst[d..].as_mut_ptr(), src[s..].as_ptr(), len) };
+        assert_eq!(ret, dst[d..].as_mut_ptr());
+        assert_eq!(dst[d..d + len], src[s..s + len]);
+        assert!(dst[..d].iter().chain(&dst[d + len..]).all(|&x| x == 0));
+
+        let chr = random() as u8 | 1;
+        let ret = unsafe { memset(dst[d..].as_mut_ptr(), chr, len) };
+        assert_eq!(ret, dst[d..].as_mut_ptr());
+        assert!(dst[d..d + len].iter().all(|&x| x == chr));
+        assert!(dst[..d].iter().chain(&dst[d + len..]).all(|&x| x == 0));
+    }
+}
+
+/// # Safety
+/// `a` and `b` must be readable for `n` bytes.
+#[cfg_attr(not(test), unsafe(no_mangle))]
+pub unsafe extern "C" fn memcmp(mut a: *const u8, mut b: *const u8, mut n: usize) -> i32 {
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    if (a as usize ^ b as usize) & 7 == 0 {
+        while n != 0 && a as usize & 7 != 0 {
+            unsafe {
+                if *a != *b {
+                    return *a as i32 - *b as i32;
+                }
+                a = a.add(1);
+                b = b.add(1);
+            }
+            n -= 1;
+        }
+        // skip equal words, the bytes below find the difference in the first other one
+        while n >= 8 && unsafe { *(a as *const u64) == *(b as *const u64) } {
+            unsafe {
+                a = a.add(8);
+                b = b.add(8);
+            }
+            n -= 8;
+        }
+    }
+    while n != 0 {
+        unsafe {
+            if *a != *b {
+                return *a as i32 - *b as i32;
+            }
+            a = a.add(1);
+            b = b.add(1);
+        }
+        n -= 1;
+    }
+    0
+}
+
+#[test]
+fn test_memcmp() {
+    let a = [7u8; 40];
+    assert_eq!(unsafe { memcmp(a.as_ptr(), [7u8; 40].as_ptr(), 40) }, 0);
+    for i in [0, 17, 39] {
+        let mut b = a;
+        b[i] = 9;
+        assert_eq!(unsafe { memcmp(a.as_ptr(), b.as_ptr(), 40) }, -2);
+        assert_eq!(unsafe { memcmp(b.as_ptr(), a.as_ptr(), 40) }, 2);
+        // unaligned against each other, and past the difference when `i == 0`
+        let expected = if i == 0 { 0 } else { -2 };
+        assert_eq!(
+            unsafe { memcmp(a[1..].as_ptr(), b[1..].as_ptr(), 39) },
+            expected
+        );
+        assert_eq!(unsafe { memcmp(a.as_ptr(), b.as_ptr(), i) }, 0);
+    }
+}
+
+/// # Safety
+/// `src` must be readable and `dst` writable for `n` bytes; they may overlap.
+#[cfg_attr(not(test), unsafe(no_mangle))]
+pub unsafe extern "C" fn memmove(dst: *mut u8, src: *const u8, n: usize) -> *mut u8 {
+    if (dst as usize).abs_diff(src as usize) >= n {
+        unsafe { memcpy(dst, src, n) };
+    } else if dst.cast_const() <= src {
+        // below the source, where a forward copy reads each byte before it's overwritten
+        for i in 0..n {
+            unsafe { *dst.add(i) = *src.add(i) };
+        }
+    } else {
+        for i in (0..n).rev() {
+            unsafe { *dst.add(i) = *src.add(i) };
+        }
+    }
+    dst
+}
+
+#[test]
+fn test_memmove() {
+    let mut buf = *b"abcdefgh";
+    unsafe { memmove(buf.as_mut_ptr(), buf[2..].as_ptr(), 5) };
+    assert_eq!(buf, *b"cdefgfgh");
+
+    let mut buf = *b"abcdefgh";
+    unsafe { memmove(buf[2..].as_mut_ptr(), buf.as_ptr(), 5) };
+    assert_eq!(buf, *b"ababcdeh");
+
+    // overlapping by less than a word, either way round
+    let mut buf: [u8; 40] = core::array::from_fn(|i| i as _);
+    unsafe { memmove(buf[3..].as_mut_ptr(), buf.as_ptr(), 32) };
+    assert!((0..32).all(|i| buf[i + 3] == i as u8));
+    let mut buf: [u8; 40] = core::array::from_fn(|i| i as _);
+    unsafe { memmove(buf.as_mut_ptr(), buf[3..].as_ptr(), 32) };
+    assert!((0..32).all(|i| buf[i] == i as u8 + 3));
+}
+
+#[test]
+fn test_mem_empty() {
+    let dangling = core::ptr::NonNull::<u8>::dangling().as_ptr();
+    unsafe {
+        assert_eq!(memcpy(dangling, dangling, 0), dangling);
+        assert_eq!(memmove(dangling, dangling, 0), dangling);
+        assert_eq!(memset(dangling, 1, 0), dangling);
+        assert_eq!(memcmp(dangling, dangling, 0), 0);
+    }
 }